@@ -1,13 +1,56 @@
-use clap::{crate_version, App, Arg};
+use std::cmp;
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+use clap::{crate_version, App, Arg, ArgMatches};
 use rustyline::Editor;
 
-use rdbc::{Connection, DataType, Result};
+use rdbc::{Connection, Error, Result, ResultSet, Row, Value};
 use rdbc_mysql::MySQLDriver;
 use rdbc_postgres::PostgresDriver;
 use rdbc_sqlite::SqliteDriver;
 
-fn main() -> Result<()> {
-    let matches = App::new("rdbc-cli")
+/// How query results are printed
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+/// Width of each column of table output when rows are not buffered, unless the column name is
+/// longer
+const FIXED_WIDTH: usize = 20;
+
+struct OutputOptions {
+    format: Format,
+    /// The text written for NULL values in CSV output
+    null: String,
+    /// Write rows as they are read rather than reading the whole result first, which means
+    /// that table columns have a fixed width
+    no_buffer: bool,
+    /// Send output through the pager named by `$PAGER`
+    pager: bool,
+}
+
+impl OutputOptions {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let format = match matches.value_of("format") {
+            Some("csv") => Format::Csv,
+            Some("json") => Format::Json,
+            _ => Format::Table,
+        };
+        OutputOptions {
+            format,
+            null: matches.value_of("null").unwrap_or("").to_owned(),
+            no_buffer: matches.is_present("no-buffer"),
+            pager: matches.is_present("pager"),
+        }
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("rdbc-cli")
         .version(crate_version!())
         .about("Rust DataBase Connectivity CLI")
         .arg(
@@ -24,10 +67,49 @@ fn main() -> Result<()> {
                 .long("connection-url")
                 .takes_value(true),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("max-rows")
+                .help("Maximum number of rows to display for each query")
+                .short("m")
+                .long("max-rows")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("Output format for query results")
+                .short("f")
+                .long("format")
+                .possible_values(&["table", "csv", "json"])
+                .default_value("table"),
+        )
+        .arg(
+            Arg::with_name("null")
+                .help("Text written for NULL values in CSV output, such as \\N")
+                .long("null")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("no-buffer")
+                .help("Print rows as they are read, with fixed-width table columns")
+                .long("no-buffer"),
+        )
+        .arg(
+            Arg::with_name("pager")
+                .help("Page query results through $PAGER")
+                .short("p")
+                .long("pager"),
+        )
+}
+
+fn main() -> Result<()> {
+    let matches = app().get_matches();
 
     let driver = matches.value_of("driver").unwrap();
     let url = matches.value_of("connection-url").unwrap();
+    let max_rows = matches
+        .value_of("max-rows")
+        .map(|n| n.parse::<u64>().expect("max-rows must be a number"));
+    let options = OutputOptions::from_matches(&matches);
     println!("Connecting to {} driver with url: {}", driver, url);
 
     let driver: Box<dyn rdbc::Driver> = match driver {
@@ -39,6 +121,16 @@ fn main() -> Result<()> {
 
     let mut conn = driver.connect(url).unwrap();
 
+    // Ctrl-C cancels the running query rather than exiting, if the driver supports it
+    if let Ok(token) = conn.cancel_token() {
+        ctrlc::set_handler(move || {
+            if let Err(e) = token.cancel() {
+                println!("Error cancelling query: {:?}", e);
+            }
+        })
+        .expect("Error setting Ctrl-C handler");
+    }
+
     let mut rl = Editor::<()>::new();
     rl.load_history(".history").ok();
 
@@ -46,13 +138,21 @@ fn main() -> Result<()> {
     loop {
         let readline = rl.readline("> ");
         match readline {
+            // meta-commands such as `.tables` are only recognized at the start of a statement
+            Ok(ref line) if query.is_empty() && line.trim_start().starts_with('.') => {
+                rl.add_history_entry(line.trim());
+                match meta_command(&mut *conn, line) {
+                    Ok(output) => print!("{}", output),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
             Ok(ref line) if line.trim_end().ends_with(';') => {
                 query.push_str(line.trim_end());
                 rl.add_history_entry(query.clone());
 
-                match execute(&mut *conn, &query) {
+                match execute(&mut *conn, &query, max_rows, &options) {
                     Ok(_) => {}
-                    Err(e) => println!("Error: {:?}", e),
+                    Err(e) => println!("Error: {}", e),
                 }
 
                 query = "".to_owned();
@@ -72,34 +172,350 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn execute(conn: &mut dyn Connection, sql: &str) -> Result<()> {
+/// Run a meta-command, borrowed from the sqlite shell, and return its output:
+///
+/// - `.tables` lists the tables in the current database
+/// - `.schema <table>` describes the columns of a table
+fn meta_command(conn: &mut dyn Connection, line: &str) -> Result<String> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    let mut output = String::new();
+    match args.as_slice() {
+        [".tables"] => {
+            for table in conn.tables()? {
+                output.push_str(&table);
+                output.push('\n');
+            }
+        }
+        [".schema", table] => {
+            let meta = conn.columns(table)?;
+            if meta.num_columns() == 0 {
+                return Err(Error::General(format!("No such table: {}", table)));
+            }
+            for i in 0..meta.num_columns() {
+                let type_name = meta
+                    .column_type_name(i)
+                    .unwrap_or_else(|| format!("{:?}", meta.column_type(i)));
+                output.push_str(&format!("{}\t{}", meta.column_name(i), type_name));
+                if let Some(default) = meta.column_default(i) {
+                    output.push_str(&format!("\tDEFAULT {}", default));
+                }
+                output.push('\n');
+            }
+        }
+        _ => {
+            return Err(Error::General(format!(
+                "Unknown command: {}. Use .tables or .schema <table>",
+                line.trim()
+            )))
+        }
+    }
+    Ok(output)
+}
+
+fn execute(
+    conn: &mut dyn Connection,
+    sql: &str,
+    max_rows: Option<u64>,
+    options: &OutputOptions,
+) -> Result<()> {
     println!("Executing {}", sql);
     let mut stmt = conn.create(sql)?;
+    if !stmt.returns_rows() {
+        let count = stmt.execute_update(&[])?;
+        println!("{} row(s) affected", count);
+        return Ok(());
+    }
+    if let Some(max_rows) = max_rows {
+        stmt.set_max_rows(max_rows)?;
+    }
     let mut rs = stmt.execute_query(&vec![])?;
-    let meta = rs.meta_data()?;
+    match pager(options) {
+        Some(mut child) => {
+            let result = match child.stdin.as_mut() {
+                Some(stdin) => write_results(&mut *rs, options, stdin),
+                None => Ok(()),
+            };
+            // closing stdin lets the pager know that there is no more output
+            drop(child.stdin.take());
+            child.wait().map_err(io_err)?;
+            result
+        }
+        None => write_results(&mut *rs, options, &mut io::stdout()),
+    }
+}
 
-    for i in 0..meta.num_columns() {
-        if i > 0 {
-            print!("\t");
+/// Start the pager named by `$PAGER`, if paging is enabled and the variable is set
+fn pager(options: &OutputOptions) -> Option<Child> {
+    if !options.pager {
+        return None;
+    }
+    let pager = std::env::var("PAGER").ok()?;
+    let mut args = pager.split_whitespace();
+    match Command::new(args.next()?)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => Some(child),
+        Err(e) => {
+            println!("Error starting pager {}: {}", pager, e);
+            None
         }
-        print!("{}", meta.column_name(i));
     }
-    println!();
+}
 
-    while rs.next() {
-        for i in 0..meta.num_columns() {
-            if i > 0 {
-                print!("\t");
+/// Read all rows of a result set and write them to `out`, including a header row for table and
+/// CSV output
+fn write_results(
+    rs: &mut dyn ResultSet,
+    options: &OutputOptions,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let meta = rs.meta_data()?;
+    let names: Vec<String> = (0..meta.num_columns())
+        .map(|i| meta.column_name(i))
+        .collect();
+    match options.format {
+        Format::Table if options.no_buffer => {
+            let widths: Vec<usize> = names
+                .iter()
+                .map(|name| cmp::max(FIXED_WIDTH, name.chars().count()))
+                .collect();
+            write_table_header(out, &names, &widths)?;
+            while let Some(row) = rs.next_row()? {
+                write_table_row(out, &table_cells(&row, names.len())?, &widths)?;
+            }
+        }
+        Format::Table => {
+            let mut rows = vec![];
+            while let Some(row) = rs.next_row()? {
+                rows.push(table_cells(&row, names.len())?);
+            }
+            let mut widths: Vec<usize> = names.iter().map(|name| name.chars().count()).collect();
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = cmp::max(*width, cell.chars().count());
+                }
+            }
+            write_table_header(out, &names, &widths)?;
+            for row in &rows {
+                write_table_row(out, row, &widths)?;
             }
-            match meta.column_type(i) {
-                DataType::Utf8 => print!("{:?}", rs.get_string(i)),
-                DataType::Integer => print!("{:?}", rs.get_i32(i)),
-                // TODO other types
-                _ => print!("{:?}", rs.get_string(i)),
+        }
+        Format::Csv => {
+            let header: Vec<String> = names.iter().map(|name| csv_field(name, None)).collect();
+            writeln!(out, "{}", header.join(",")).map_err(io_err)?;
+            while let Some(row) = rs.next_row()? {
+                let fields: Vec<String> = row
+                    .get_row_values()?
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => options.null.clone(),
+                        _ => csv_field(&value.to_display_string(), Some(&options.null)),
+                    })
+                    .collect();
+                writeln!(out, "{}", fields.join(",")).map_err(io_err)?;
+            }
+        }
+        Format::Json => {
+            write!(out, "[").map_err(io_err)?;
+            let mut first = true;
+            while let Some(row) = rs.next_row()? {
+                let fields: Vec<String> = names
+                    .iter()
+                    .zip(row.get_row_values()?.iter())
+                    .map(|(name, value)| format!("{}:{}", json_string(name), json_value(value)))
+                    .collect();
+                if !first {
+                    writeln!(out, ",").map_err(io_err)?;
+                }
+                first = false;
+                write!(out, "{{{}}}", fields.join(",")).map_err(io_err)?;
             }
+            writeln!(out, "]").map_err(io_err)?;
         }
-        println!();
     }
-
     Ok(())
 }
+
+fn io_err(e: io::Error) -> Error {
+    Error::General(e.to_string())
+}
+
+/// Format the cells of a row using the driver's display formatting of each column
+fn table_cells(row: &Row<'_>, columns: usize) -> Result<Vec<String>> {
+    (0..columns as u64).map(|i| row.get_display(i)).collect()
+}
+
+fn write_table_header(out: &mut dyn Write, names: &[String], widths: &[usize]) -> Result<()> {
+    write_table_row(out, names, widths)?;
+    let dashes: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    writeln!(out, "{}", dashes.join("-+-")).map_err(io_err)
+}
+
+/// Write a row of table output, padding each cell to the width of its column. The last cell is
+/// not padded, to avoid trailing spaces.
+fn write_table_row(out: &mut dyn Write, cells: &[String], widths: &[usize]) -> Result<()> {
+    let last = cells.len().saturating_sub(1);
+    let cells: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(i, (cell, width))| {
+            if i == last {
+                cell.clone()
+            } else {
+                format!("{:<1$}", cell, width)
+            }
+        })
+        .collect();
+    writeln!(out, "{}", cells.join(" | ")).map_err(io_err)
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break, or if it would
+/// otherwise be read as NULL
+fn csv_field(s: &str, null: Option<&str>) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') || Some(s) == null {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int8(_) | Value::Int16(_) | Value::Int32(_) | Value::Int64(_) | Value::UInt32(_) => {
+            value.to_string()
+        }
+        Value::Float32(n) if n.is_finite() => value.to_string(),
+        Value::Float64(n) if n.is_finite() => value.to_string(),
+        _ => json_string(&value.to_display_string()),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdbc::Driver;
+
+    fn format_results(rs: &mut dyn ResultSet, options: &OutputOptions) -> Result<String> {
+        let mut out = vec![];
+        write_results(rs, options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn meta_commands() -> Result<()> {
+        let mut conn = SqliteDriver::new().connect("")?;
+        conn.execute(
+            "CREATE TABLE orders (id INT, note TEXT DEFAULT 'none')",
+            &[],
+        )?;
+        conn.execute("CREATE TABLE customers (id INT)", &[])?;
+
+        assert_eq!("customers\norders\n", meta_command(&mut *conn, ".tables")?);
+        assert_eq!(
+            "id\tINT\nnote\tTEXT\tDEFAULT 'none'\n",
+            meta_command(&mut *conn, " .schema  orders ")?
+        );
+        assert!(meta_command(&mut *conn, ".schema missing").is_err());
+        assert!(meta_command(&mut *conn, ".help").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn table_output() -> Result<()> {
+        let mut conn = SqliteDriver::new().connect("")?;
+        conn.execute("CREATE TABLE test (id INT, name TEXT)", &[])?;
+        conn.execute(
+            "INSERT INTO test (id, name) VALUES (1, NULL), (22, 'a,b'), (333, 'longer name')",
+            &[],
+        )?;
+
+        let matches = app().get_matches_from(vec!["rdbc-cli"]);
+        let options = OutputOptions::from_matches(&matches);
+        let mut rs = conn.query("SELECT id, name FROM test ORDER BY id", &[])?;
+        assert_eq!(
+            "id  | name\n\
+             ----+------------\n\
+             1   | NULL\n\
+             22  | a,b\n\
+             333 | longer name\n",
+            format_results(&mut *rs, &options)?
+        );
+
+        // unbuffered output cannot measure the values so uses fixed widths
+        let matches = app().get_matches_from(vec!["rdbc-cli", "--no-buffer"]);
+        let options = OutputOptions::from_matches(&matches);
+        let mut rs = conn.query("SELECT name, id FROM test WHERE id = 22", &[])?;
+        assert_eq!(
+            format!(
+                "name{} | id\n{}-+-{}\na,b{} | 22\n",
+                " ".repeat(16),
+                "-".repeat(20),
+                "-".repeat(20),
+                " ".repeat(17)
+            ),
+            format_results(&mut *rs, &options)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn csv_output() -> Result<()> {
+        let mut conn = SqliteDriver::new().connect("")?;
+        conn.execute("CREATE TABLE test (id INT, name TEXT)", &[])?;
+        conn.execute(
+            "INSERT INTO test (id, name) VALUES (1, NULL), (2, 'a,b'), (3, '\\N')",
+            &[],
+        )?;
+
+        let matches = app().get_matches_from(vec!["rdbc-cli", "--format", "csv", "--null", "\\N"]);
+        let options = OutputOptions::from_matches(&matches);
+        let mut rs = conn.query("SELECT id, name FROM test ORDER BY id", &[])?;
+        assert_eq!(
+            "id,name\n1,\\N\n2,\"a,b\"\n3,\"\\N\"\n",
+            format_results(&mut *rs, &options)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_output() -> Result<()> {
+        let mut conn = SqliteDriver::new().connect("")?;
+        conn.execute("CREATE TABLE test (id INT, name TEXT)", &[])?;
+        conn.execute(
+            "INSERT INTO test (id, name) VALUES (1, NULL), (2, 'say \"hi\"')",
+            &[],
+        )?;
+
+        let matches = app().get_matches_from(vec!["rdbc-cli", "-f", "json", "--null", "\\N"]);
+        let options = OutputOptions::from_matches(&matches);
+        let mut rs = conn.query("SELECT id, name FROM test ORDER BY id", &[])?;
+        assert_eq!(
+            "[{\"id\":1,\"name\":null},\n{\"id\":2,\"name\":\"say \\\"hi\\\"\"}]\n",
+            format_results(&mut *rs, &options)?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,699 @@
+//! Conversions between the Postgres binary wire format and RDBC values
+
+use postgres::types::{IsNull, Kind, ToSql, Type};
+
+/// SQL NULL parameter that can be bound to a placeholder of any type
+#[derive(Debug)]
+pub(crate) struct Null;
+
+impl ToSql for Null {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        _out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(IsNull::Yes)
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.to_sql(ty, out)
+    }
+}
+
+/// RDBC value bound as a Postgres parameter. The value is borrowed so that strings and bytes
+/// are serialized directly from the caller's parameters without being copied first.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PgValue<'a>(pub &'a rdbc::Value);
+
+impl<'a> PgValue<'a> {
+    /// Call `f` with the equivalent postgres parameter
+    fn with_param<F>(&self, f: F) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>>
+    where
+        F: FnOnce(&dyn ToSql) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>>,
+    {
+        match self.0 {
+            rdbc::Value::Null => f(&Null),
+            rdbc::Value::Bool(b) => f(b),
+            rdbc::Value::String(s) => f(&s.as_str()),
+            rdbc::Value::Char(c) => f(&c.to_string().as_str()),
+            // postgres only accepts i8 for the internal "char" type so widen to int2
+            rdbc::Value::Int8(n) => f(&i16::from(*n)),
+            rdbc::Value::Int16(n) => f(n),
+            rdbc::Value::Int32(n) => f(n),
+            rdbc::Value::Int64(n) => f(n),
+            rdbc::Value::UInt32(n) => f(n),
+            rdbc::Value::Float32(n) => f(n),
+            rdbc::Value::Float64(n) => f(n),
+            rdbc::Value::Bytes(b) => f(&b.as_slice()),
+            rdbc::Value::Interval(i) => f(&Interval(*i)),
+            rdbc::Value::Record(_) => Err("Record parameters are not supported by Postgres".into()),
+            rdbc::Value::Range { .. } => {
+                Err("Range parameters are not supported by Postgres".into())
+            }
+            rdbc::Value::Array(_) => Err("Array parameters are not supported by Postgres".into()),
+            #[cfg(feature = "bigdecimal")]
+            rdbc::Value::Decimal(d) => f(&Numeric(&d.to_string())),
+            //TODO all types
+            v => Err(format!("Unsupported parameter: {:?}", v).into()),
+        }
+    }
+}
+
+impl<'a> ToSql for PgValue<'a> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.with_param(|param| param.to_sql(ty, out))
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        // the wrapped parameter checks the type when it is serialized
+        true
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.with_param(|param| param.to_sql_checked(ty, out))
+    }
+}
+
+/// The number of parameters that can be bound without allocating
+const INLINE_PARAMS: usize = 16;
+
+/// Call `f` with the RDBC parameters wrapped as postgres parameters. Up to `INLINE_PARAMS`
+/// parameters are wrapped on the stack, so executing a statement with a few parameters does not
+/// allocate to bind them.
+pub(crate) fn bind_params<R, F>(params: &[rdbc::Value], f: F) -> R
+where
+    F: FnOnce(&[&dyn ToSql]) -> R,
+{
+    let first = match params.first() {
+        Some(first) if params.len() <= INLINE_PARAMS => first,
+        Some(_) => {
+            let values: Vec<PgValue> = params.iter().map(PgValue).collect();
+            let refs: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+            return f(&refs);
+        }
+        None => return f(&[]),
+    };
+    // the unused slots are filled with the first parameter and are not passed to `f`
+    let mut values = [PgValue(first); INLINE_PARAMS];
+    for (value, param) in values.iter_mut().zip(params) {
+        *value = PgValue(param);
+    }
+    let mut refs: [&dyn ToSql; INLINE_PARAMS] = [&values[0]; INLINE_PARAMS];
+    for (r, value) in refs.iter_mut().zip(&values) {
+        *r = value;
+    }
+    f(&refs[..params.len()])
+}
+
+/// Interval parameter, encoded using the Postgres binary format
+#[derive(Debug)]
+pub(crate) struct Interval(pub rdbc::Interval);
+
+impl ToSql for Interval {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(&self.0.microseconds.to_be_bytes());
+        out.extend_from_slice(&self.0.days.to_be_bytes());
+        out.extend_from_slice(&self.0.months.to_be_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "interval"
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        if !Self::accepts(ty) {
+            return Err(format!("Cannot convert interval to {}", ty.name()).into());
+        }
+        self.to_sql(ty, out)
+    }
+}
+
+/// Decimal parameter given as a string of decimal digits, encoded using the Postgres binary
+/// format for `numeric` values
+#[derive(Debug)]
+pub(crate) struct Numeric<'a>(pub &'a str);
+
+impl<'a> ToSql for Numeric<'a> {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.extend_from_slice(&encode_numeric(self.0)?);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "numeric"
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut Vec<u8>,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        if !Self::accepts(ty) {
+            return Err(format!("Cannot convert decimal to {}", ty.name()).into());
+        }
+        self.to_sql(ty, out)
+    }
+}
+
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+
+/// Encode a decimal string such as `-123.45` in the Postgres binary format for `numeric`
+/// values, which is the number of base-10000 digits, the weight of the first digit, the sign
+/// and the number of decimal places, followed by the digits
+fn encode_numeric(s: &str) -> rdbc::Result<Vec<u8>> {
+    let invalid = || rdbc::Error::Conversion(format!("Invalid decimal: {}", s));
+    let (negative, unsigned) = if s.starts_with('-') {
+        (true, &s[1..])
+    } else {
+        (false, s.trim_start_matches('+'))
+    };
+    let mut parts = unsigned.splitn(2, '.');
+    let int_part = parts.next().unwrap_or("").trim_start_matches('0');
+    let frac_part = parts.next().unwrap_or("");
+    if !unsigned.chars().any(|c| c.is_ascii_digit())
+        || !int_part
+            .chars()
+            .chain(frac_part.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    // pad the integer part on the left and the fraction on the right to whole base-10000 digits
+    let int_groups = (int_part.len() + 3) / 4;
+    let frac_groups = (frac_part.len() + 3) / 4;
+    let padded = format!(
+        "{}{}{}{}",
+        "0".repeat(int_groups * 4 - int_part.len()),
+        int_part,
+        frac_part,
+        "0".repeat(frac_groups * 4 - frac_part.len())
+    );
+    let mut digits: Vec<i16> = padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap().parse().unwrap())
+        .collect();
+    let mut weight = int_groups as i32 - 1;
+    let leading_zeros = digits.iter().take_while(|d| **d == 0).count();
+    digits.drain(..leading_zeros);
+    weight -= leading_zeros as i32;
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    let mut out = Vec::with_capacity(8 + digits.len() * 2);
+    out.extend_from_slice(&(digits.len() as i16).to_be_bytes());
+    out.extend_from_slice(&(weight as i16).to_be_bytes());
+    let sign = if negative && !digits.is_empty() {
+        NUMERIC_NEG
+    } else {
+        0
+    };
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(frac_part.len() as u16).to_be_bytes());
+    for digit in digits {
+        out.extend_from_slice(&digit.to_be_bytes());
+    }
+    Ok(out)
+}
+
+/// Decode a `numeric` value from the Postgres binary format into a decimal string, with as many
+/// decimal places as the value's scale
+pub(crate) fn decode_numeric(raw: &[u8]) -> rdbc::Result<String> {
+    let mut buf = raw;
+    let count = i16::from_be_bytes(read(take(&mut buf, 2)?)?);
+    let weight = i32::from(i16::from_be_bytes(read(take(&mut buf, 2)?)?));
+    let sign = u16::from_be_bytes(read(take(&mut buf, 2)?)?);
+    let scale = usize::from(u16::from_be_bytes(read(take(&mut buf, 2)?)?));
+    if sign == NUMERIC_NAN {
+        return Ok("NaN".to_owned());
+    }
+    let digits = (0..count)
+        .map(|_| Ok(i16::from_be_bytes(read(take(&mut buf, 2)?)?)))
+        .collect::<rdbc::Result<Vec<i16>>>()?;
+    // the digit with weight `w` is at index `weight - w`, and missing digits are zero
+    let digit = |w: i32| {
+        let i = weight - w;
+        if i >= 0 && (i as usize) < digits.len() {
+            digits[i as usize]
+        } else {
+            0
+        }
+    };
+
+    let mut s = String::new();
+    if sign == NUMERIC_NEG {
+        s.push('-');
+    }
+    if weight < 0 {
+        s.push('0');
+    } else {
+        s.push_str(&digit(weight).to_string());
+        for w in (0..weight).rev() {
+            s.push_str(&format!("{:04}", digit(w)));
+        }
+    }
+    if scale > 0 {
+        let frac: String = (1..=(scale as i32 + 3) / 4)
+            .map(|w| format!("{:04}", digit(-w)))
+            .collect();
+        s.push('.');
+        s.push_str(&frac[..scale]);
+    }
+    Ok(s)
+}
+
+/// Decode an interval from the Postgres binary format, which is the time component in
+/// microseconds followed by the days and months
+pub(crate) fn decode_interval(raw: &[u8]) -> rdbc::Result<rdbc::Interval> {
+    if raw.len() != 16 {
+        return Err(rdbc::Error::General(format!(
+            "Invalid interval length: {}",
+            raw.len()
+        )));
+    }
+    let mut microseconds = [0; 8];
+    let mut days = [0; 4];
+    let mut months = [0; 4];
+    microseconds.copy_from_slice(&raw[0..8]);
+    days.copy_from_slice(&raw[8..12]);
+    months.copy_from_slice(&raw[12..16]);
+    Ok(rdbc::Interval {
+        months: i32::from_be_bytes(months),
+        days: i32::from_be_bytes(days),
+        microseconds: i64::from_be_bytes(microseconds),
+    })
+}
+
+/// Decode a value received in the Postgres binary format. Only scalar types with an
+/// equivalent `rdbc::Value`, and records and ranges made up of those types, are supported.
+pub(crate) fn decode_value(ty: &Type, raw: &[u8]) -> rdbc::Result<rdbc::Value> {
+    if let Kind::Range(element) = ty.kind() {
+        return decode_range(element, raw);
+    }
+    Ok(match ty.name() {
+        "bool" => rdbc::Value::Bool(raw.first() != Some(&0)),
+        "int2" => rdbc::Value::Int16(i16::from_be_bytes(read(raw)?)),
+        "int4" => rdbc::Value::Int32(i32::from_be_bytes(read(raw)?)),
+        "int8" => rdbc::Value::Int64(i64::from_be_bytes(read(raw)?)),
+        "oid" => rdbc::Value::UInt32(u32::from_be_bytes(read(raw)?)),
+        "float4" => rdbc::Value::Float32(f32::from_bits(u32::from_be_bytes(read(raw)?))),
+        "float8" => rdbc::Value::Float64(f64::from_bits(u64::from_be_bytes(read(raw)?))),
+        "text" | "varchar" | "bpchar" | "name" => rdbc::Value::String(
+            String::from_utf8(raw.to_vec()).map_err(|e| rdbc::Error::General(e.to_string()))?,
+        ),
+        "bytea" => rdbc::Value::Bytes(raw.to_vec()),
+        "interval" => rdbc::Value::Interval(decode_interval(raw)?),
+        "record" => rdbc::Value::Record(decode_record(raw)?),
+        name => {
+            return Err(rdbc::Error::NotSupported(format!(
+                "Cannot decode Postgres {} value",
+                name
+            )))
+        }
+    })
+}
+
+/// Decode a record (composite value) from the Postgres binary format, which is the number of
+/// fields followed by the type OID, length and value of each field. A length of -1 indicates
+/// that the field is NULL.
+pub(crate) fn decode_record(raw: &[u8]) -> rdbc::Result<Vec<rdbc::Value>> {
+    let mut buf = raw;
+    let count = i32::from_be_bytes(read(take(&mut buf, 4)?)?);
+    let mut fields = Vec::with_capacity(count.max(0) as usize);
+    for _ in 0..count {
+        let oid = u32::from_be_bytes(read(take(&mut buf, 4)?)?);
+        let len = i32::from_be_bytes(read(take(&mut buf, 4)?)?);
+        if len < 0 {
+            fields.push(rdbc::Value::Null);
+            continue;
+        }
+        let raw = take(&mut buf, len as usize)?;
+        let ty = Type::from_oid(oid).ok_or_else(|| {
+            rdbc::Error::NotSupported(format!("Cannot decode record field with type {}", oid))
+        })?;
+        fields.push(decode_value(&ty, raw)?);
+    }
+    Ok(fields)
+}
+
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LOWER_INC: u8 = 0x02;
+const RANGE_UPPER_INC: u8 = 0x04;
+const RANGE_LOWER_INF: u8 = 0x08;
+const RANGE_UPPER_INF: u8 = 0x10;
+
+/// Decode a range from the Postgres binary format, which is a byte of flags followed by the
+/// length and value of each bound that is present. Bounds of types without an equivalent
+/// `rdbc::Value`, such as timestamps, are returned as strings formatted by `display`.
+pub(crate) fn decode_range(element: &Type, raw: &[u8]) -> rdbc::Result<rdbc::Value> {
+    let mut buf = raw;
+    let flags = take(&mut buf, 1)?[0];
+    if flags & RANGE_EMPTY != 0 {
+        return Ok(rdbc::Value::Range {
+            lower: None,
+            upper: None,
+            lower_inc: true,
+            upper_inc: true,
+        });
+    }
+    let mut bound = |present: bool| -> rdbc::Result<Option<Box<rdbc::Value>>> {
+        if !present {
+            return Ok(None);
+        }
+        let len = i32::from_be_bytes(read(take(&mut buf, 4)?)?);
+        let raw = take(&mut buf, len.max(0) as usize)?;
+        let value = match decode_value(element, raw) {
+            Err(rdbc::Error::NotSupported(_)) => rdbc::Value::String(display(element, raw)?),
+            value => value?,
+        };
+        Ok(Some(Box::new(value)))
+    };
+    let lower = bound(flags & RANGE_LOWER_INF == 0)?;
+    let upper = bound(flags & RANGE_UPPER_INF == 0)?;
+    Ok(rdbc::Value::Range {
+        lower,
+        upper,
+        lower_inc: flags & RANGE_LOWER_INC != 0,
+        upper_inc: flags & RANGE_UPPER_INC != 0,
+    })
+}
+
+/// Split the first `n` bytes from a buffer
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> rdbc::Result<&'a [u8]> {
+    if buf.len() < n {
+        return Err(rdbc::Error::General("Unexpected end of value".to_owned()));
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Ok(head)
+}
+
+/// Days between the Unix epoch and the Postgres epoch of 2000-01-01
+const PG_EPOCH_DAYS: i64 = 10_957;
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// Format a value received in the Postgres binary format for display. Temporal values are
+/// formatted as ISO-8601, bytes as hex and booleans as `t`/`f`.
+pub(crate) fn display(ty: &Type, raw: &[u8]) -> rdbc::Result<String> {
+    if let Kind::Range(element) = ty.kind() {
+        return decode_range(element, raw).map(|range| range.to_display_string());
+    }
+    Ok(match ty.name() {
+        "bool" => if raw.first() == Some(&0) { "f" } else { "t" }.to_owned(),
+        "int2" => i16::from_be_bytes(read(raw)?).to_string(),
+        "int4" => i32::from_be_bytes(read(raw)?).to_string(),
+        "int8" => i64::from_be_bytes(read(raw)?).to_string(),
+        "oid" => u32::from_be_bytes(read(raw)?).to_string(),
+        "float4" => f32::from_bits(u32::from_be_bytes(read(raw)?)).to_string(),
+        "float8" => f64::from_bits(u64::from_be_bytes(read(raw)?)).to_string(),
+        "date" => match i32::from_be_bytes(read(raw)?) {
+            std::i32::MAX => "infinity".to_owned(),
+            std::i32::MIN => "-infinity".to_owned(),
+            days => format_date(i64::from(days)),
+        },
+        "time" => format_time(i64::from_be_bytes(read(raw)?)),
+        "timestamp" => format_timestamp(i64::from_be_bytes(read(raw)?)),
+        "timestamptz" => format!("{}Z", format_timestamp(i64::from_be_bytes(read(raw)?))),
+        "interval" => decode_interval(raw)?.to_string(),
+        "numeric" => decode_numeric(raw)?,
+        "record" => rdbc::Value::Record(decode_record(raw)?).to_display_string(),
+        "bytea" => format!("\\x{}", to_hex(raw)),
+        _ => match std::str::from_utf8(raw) {
+            Ok(s) => s.to_owned(),
+            Err(_) => format!("\\x{}", to_hex(raw)),
+        },
+    })
+}
+
+/// Read a fixed size value from a buffer, validating its length
+fn read<T: Default + AsMut<[u8]>>(raw: &[u8]) -> rdbc::Result<T> {
+    let mut buf = T::default();
+    if buf.as_mut().len() != raw.len() {
+        return Err(rdbc::Error::General(format!(
+            "Invalid value length: {}",
+            raw.len()
+        )));
+    }
+    buf.as_mut().copy_from_slice(raw);
+    Ok(buf)
+}
+
+/// Decode the text representation of a `bytea` value, in either the hex format (`\x` followed by
+/// two hex digits per byte) or the escape format (printable bytes as-is, `\\` for a backslash
+/// and `\nnn` in octal for any other byte), depending on the `bytea_output` setting
+pub(crate) fn decode_bytea_text(text: &str) -> rdbc::Result<Vec<u8>> {
+    let invalid = || rdbc::Error::Conversion(format!("Invalid bytea value: {}", text));
+    if text.starts_with("\\x") {
+        let hex = text[2..].as_bytes();
+        if hex.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        return hex
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect();
+    }
+
+    let raw = text.as_bytes();
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] != b'\\' {
+            bytes.push(raw[i]);
+            i += 1;
+        } else if raw.get(i + 1) == Some(&b'\\') {
+            bytes.push(b'\\');
+            i += 2;
+        } else {
+            let octal = raw.get(i + 1..i + 4).ok_or_else(invalid)?;
+            let octal = std::str::from_utf8(octal).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(octal, 8).map_err(|_| invalid())?);
+            i += 4;
+        }
+    }
+    Ok(bytes)
+}
+
+fn to_hex(raw: &[u8]) -> String {
+    raw.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Format a date given as days since the Postgres epoch
+fn format_date(days: i64) -> String {
+    let (y, m, d) = civil_from_days(days + PG_EPOCH_DAYS);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Format a time of day given as microseconds since midnight
+fn format_time(micros: i64) -> String {
+    let secs = micros / 1_000_000;
+    let frac = micros % 1_000_000;
+    let mut s = format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    );
+    if frac != 0 {
+        let frac = format!(".{:06}", frac);
+        s.push_str(frac.trim_end_matches('0'));
+    }
+    s
+}
+
+/// Format a timestamp given as microseconds since the Postgres epoch
+fn format_timestamp(micros: i64) -> String {
+    match micros {
+        std::i64::MAX => "infinity".to_owned(),
+        std::i64::MIN => "-infinity".to_owned(),
+        _ => format!(
+            "{}T{}",
+            format_date(micros.div_euclid(MICROS_PER_DAY)),
+            format_time(micros.rem_euclid(MICROS_PER_DAY))
+        ),
+    }
+}
+
+/// Convert days since the Unix epoch into a (year, month, day) date in the proleptic
+/// Gregorian calendar, using Howard Hinnant's `civil_from_days` algorithm
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() -> rdbc::Result<()> {
+        // (42, 'a', NULL) with int4, text and int4 fields
+        let mut raw = vec![];
+        raw.extend_from_slice(&3i32.to_be_bytes());
+        raw.extend_from_slice(&23u32.to_be_bytes());
+        raw.extend_from_slice(&4i32.to_be_bytes());
+        raw.extend_from_slice(&42i32.to_be_bytes());
+        raw.extend_from_slice(&25u32.to_be_bytes());
+        raw.extend_from_slice(&1i32.to_be_bytes());
+        raw.extend_from_slice(b"a");
+        raw.extend_from_slice(&23u32.to_be_bytes());
+        raw.extend_from_slice(&(-1i32).to_be_bytes());
+
+        assert_eq!(
+            vec![
+                rdbc::Value::Int32(42),
+                rdbc::Value::String("a".to_owned()),
+                rdbc::Value::Null
+            ],
+            decode_record(&raw)?
+        );
+        assert!(decode_record(&raw[0..raw.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ranges() -> rdbc::Result<()> {
+        let int4 = Type::from_oid(23).unwrap();
+        // [1,5) and (,5]
+        let mut raw = vec![RANGE_LOWER_INC];
+        raw.extend_from_slice(&4i32.to_be_bytes());
+        raw.extend_from_slice(&1i32.to_be_bytes());
+        raw.extend_from_slice(&4i32.to_be_bytes());
+        raw.extend_from_slice(&5i32.to_be_bytes());
+        assert_eq!(
+            rdbc::Value::Range {
+                lower: Some(Box::new(rdbc::Value::Int32(1))),
+                upper: Some(Box::new(rdbc::Value::Int32(5))),
+                lower_inc: true,
+                upper_inc: false,
+            },
+            decode_range(&int4, &raw)?
+        );
+
+        let mut raw = vec![RANGE_LOWER_INF | RANGE_UPPER_INC];
+        raw.extend_from_slice(&4i32.to_be_bytes());
+        raw.extend_from_slice(&5i32.to_be_bytes());
+        assert_eq!("(,5]", decode_range(&int4, &raw)?.to_display_string());
+        assert_eq!(
+            "empty",
+            decode_range(&int4, &[RANGE_EMPTY])?.to_display_string()
+        );
+        assert!(decode_range(&int4, &raw[0..3]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bytea_text() -> rdbc::Result<()> {
+        let bytes = vec![0, 0xff, b'\\', b'a'];
+        assert_eq!(bytes, decode_bytea_text("\\x00ff5c61")?);
+        assert_eq!(bytes, decode_bytea_text("\\x00FF5C61")?);
+        assert_eq!(bytes, decode_bytea_text("\\000\\377\\\\a")?);
+        assert_eq!(b"plain".to_vec(), decode_bytea_text("plain")?);
+        assert!(decode_bytea_text("\\x0").is_err());
+        assert!(decode_bytea_text("\\xzz").is_err());
+        assert!(decode_bytea_text("\\09").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bind() {
+        let params: Vec<rdbc::Value> = (0..20).map(rdbc::Value::Int32).collect();
+        for n in &[0, 1, INLINE_PARAMS, INLINE_PARAMS + 1, 20] {
+            let bound = bind_params(&params[..*n], |p| format!("{:?}", p));
+            let expected: Vec<PgValue> = params[..*n].iter().map(PgValue).collect();
+            assert_eq!(format!("{:?}", expected), bound);
+        }
+    }
+
+    #[test]
+    fn numerics() -> rdbc::Result<()> {
+        let mut expected = vec![];
+        for n in &[2i16, 0, 0, 1, 12, 5000] {
+            expected.extend_from_slice(&n.to_be_bytes());
+        }
+        assert_eq!(expected, encode_numeric("12.5")?);
+
+        for s in &[
+            "1234567890123456789012345678901234.567890",
+            "-0.00012",
+            "0",
+            "0.000",
+            "100000000",
+            "-12345.6789",
+        ] {
+            assert_eq!(*s, decode_numeric(&encode_numeric(s)?)?);
+        }
+        assert_eq!("12.5", decode_numeric(&encode_numeric("+0012.5")?)?);
+        assert!(encode_numeric("").is_err());
+        assert!(encode_numeric("-.").is_err());
+        assert!(encode_numeric("1e5").is_err());
+        assert!(encode_numeric("1.2.3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!("2000-01-01", format_date(0));
+        assert_eq!("1999-12-31", format_date(-1));
+        assert_eq!("2020-02-29", format_date(7364));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!("2000-01-01T00:00:00", format_timestamp(0));
+        assert_eq!("1999-12-31T23:59:59.5", format_timestamp(-500_000));
+        assert_eq!(
+            "2000-01-02T01:02:03.000004",
+            format_timestamp(MICROS_PER_DAY + 3_723_000_004)
+        );
+    }
+}
@@ -14,18 +14,55 @@
 //! let stmt = conn.prepare("INSERT INTO test (a) VALUES (?)").unwrap().execute_update(&[rdbc::Value::Int32(123)]).unwrap();
 //! let mut stmt = conn.prepare("SELECT a FROM test").unwrap();
 //! let mut rs = stmt.execute_query(&[]).unwrap();
-//! assert!(rs.next());
+//! assert!(rs.next().unwrap());
 //! assert_eq!(Some(123), rs.get_i32(0).unwrap());
 //! ```
+//!
+//! An empty URL opens a private in-memory database. Any other URL is treated as a path or a
+//! SQLite URI filename (e.g. `file:data.db` or `file:shared?mode=memory&cache=shared`). In
+//! addition to the query parameters understood by SQLite, `journal_mode` may be specified to
+//! set the journal mode when the connection is opened, e.g. `file:data.db?journal_mode=WAL`,
+//! and `read_only=true` may be specified to open the database with `SQLITE_OPEN_READ_ONLY`.
+//!
+//! Arrays can be bound as parameters using the `rarray` table-valued function from SQLite's
+//! `carray` extension, e.g. `SELECT * FROM t WHERE x IN rarray(?)`.
+//!
+//! SQLite only allows the read-only open flag to be set when a database is opened, so
+//! `Connection::set_read_only` is implemented using `PRAGMA query_only`, which prevents any
+//! changes from being made through the connection.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::io::Read;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
 
 use fallible_streaming_iterator::FallibleStreamingIterator;
-use rusqlite::Rows;
+use log::{debug, warn};
+use rusqlite::types::{FromSql, FromSqlError, Null, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{DatabaseName, OpenFlags, Rows};
+
+use rdbc::Dialect;
 
 /// Convert a Sqlite error into an RDBC error
 fn to_rdbc_err(e: rusqlite::Error) -> rdbc::Error {
     rdbc::Error::General(format!("{:?}", e))
 }
 
+/// Convert a Sqlite error into an RDBC error, first recording the details of any error reported
+/// by SQLite as the connection's last error
+fn record_err(last_error: &RefCell<Option<rdbc::DbError>>, e: rusqlite::Error) -> rdbc::Error {
+    if let rusqlite::Error::SqliteFailure(err, msg) = &e {
+        *last_error.borrow_mut() = Some(rdbc::DbError {
+            code: err.extended_code.to_string(),
+            message: msg.clone().unwrap_or_else(|| err.to_string()),
+            ..Default::default()
+        });
+    }
+    to_rdbc_err(e)
+}
+
 pub struct SqliteDriver {}
 
 impl SqliteDriver {
@@ -35,35 +72,436 @@ impl SqliteDriver {
 }
 
 impl rdbc::Driver for SqliteDriver {
-    fn connect(&self, _url: &str) -> rdbc::Result<Box<dyn rdbc::Connection>> {
-        let c = rusqlite::Connection::open_in_memory().map_err(to_rdbc_err)?;
+    /// Opening a SQLite database is a local operation so the timeout is ignored
+    fn connect_timeout(
+        &self,
+        url: &str,
+        _timeout: Duration,
+    ) -> rdbc::Result<Box<dyn rdbc::Connection>> {
+        let c = open(url)?;
         Ok(Box::new(SConnection::new(c)))
     }
+
+    /// Only `database`, which is the path or URI filename of the database, and `params` are
+    /// used. An empty or missing `database` with no `params` opens a private in-memory database.
+    fn connect_with(&self, opts: &rdbc::ConnectOptions) -> rdbc::Result<Box<dyn rdbc::Connection>> {
+        let path = opts.database.as_ref().map(String::as_str).unwrap_or("");
+        let c = if path.is_empty() && opts.params.is_empty() {
+            rusqlite::Connection::open_in_memory().map_err(to_rdbc_err)?
+        } else {
+            let params: Vec<(&str, &str)> = opts
+                .params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            open_with_params(path, &params)?
+        };
+        Ok(Box::new(SConnection::new(c)))
+    }
+}
+
+/// Open a database, applying any options specified in the URL query string
+fn open(url: &str) -> rdbc::Result<rusqlite::Connection> {
+    if url.is_empty() {
+        return rusqlite::Connection::open_in_memory().map_err(to_rdbc_err);
+    }
+
+    let mut parts = url.splitn(2, '?');
+    let path = parts.next().unwrap_or("");
+    let query = parts.next().unwrap_or("");
+    let params: Vec<(&str, &str)> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|param| {
+            let mut kv = param.splitn(2, '=');
+            (kv.next().unwrap_or(""), kv.next().unwrap_or(""))
+        })
+        .collect();
+    open_with_params(path, &params)
+}
+
+/// Open a database, applying any of the options that are not SQLite URI parameters and passing
+/// the rest through in the URI filename
+fn open_with_params(path: &str, params: &[(&str, &str)]) -> rdbc::Result<rusqlite::Connection> {
+    let mut flags = OpenFlags::default() | OpenFlags::SQLITE_OPEN_URI;
+    let mut journal_mode = None;
+    let mut uri_params = vec![];
+    for &(name, value) in params {
+        let param = format!("{}={}", name, value);
+        match name {
+            "journal_mode" => {
+                if !value.chars().all(char::is_alphanumeric) {
+                    return Err(rdbc::Error::General(format!(
+                        "Invalid journal_mode: {}",
+                        value
+                    )));
+                }
+                journal_mode = Some(value);
+            }
+            "read_only" if value == "true" => {
+                flags.remove(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE);
+                flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
+            }
+            "cache" if value == "shared" => {
+                flags |= OpenFlags::SQLITE_OPEN_SHARED_CACHE;
+                uri_params.push(param);
+            }
+            _ => uri_params.push(param),
+        }
+    }
+
+    let path = if uri_params.is_empty() {
+        path.to_owned()
+    } else {
+        format!("{}?{}", path, uri_params.join("&"))
+    };
+
+    let conn = rusqlite::Connection::open_with_flags(path, flags).map_err(to_rdbc_err)?;
+    if let Some(journal_mode) = journal_mode {
+        conn.execute_batch(&format!("PRAGMA journal_mode = {}", journal_mode))
+            .map_err(to_rdbc_err)?;
+    }
+    Ok(conn)
 }
 
-struct SConnection {
+/// A connection to a SQLite database. Use `rdbc::Connection::as_any` to downcast to this type.
+pub struct SConnection {
     conn: rusqlite::Connection,
+    /// Whether the `rarray` module was registered, so that arrays can be bound as parameters
+    arrays: bool,
+    last_error: RefCell<Option<rdbc::DbError>>,
 }
 
 impl SConnection {
     pub fn new(conn: rusqlite::Connection) -> Self {
-        Self { conn }
+        let arrays = match rusqlite::vtab::array::load_module(&conn) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to load the carray module: {:?}", e);
+                false
+            }
+        };
+        Self {
+            conn,
+            arrays,
+            last_error: RefCell::new(None),
+        }
+    }
+
+    /// The underlying `rusqlite::Connection`. Its API is governed by the `rusqlite` crate rather
+    /// than rdbc and may change when the driver upgrades that dependency.
+    pub fn raw(&self) -> &rusqlite::Connection {
+        &self.conn
+    }
+
+    /// Mutable access to the underlying `rusqlite::Connection`
+    pub fn raw_mut(&mut self) -> &mut rusqlite::Connection {
+        &mut self.conn
+    }
+
+    /// Open a reader over a BLOB value using SQLite's incremental I/O, so that large values can
+    /// be read without loading them into memory. The value is identified by its table, column
+    /// and rowid since SQLite does not track where the values in a result set came from.
+    pub fn blob_reader(
+        &self,
+        table: &str,
+        column: &str,
+        rowid: i64,
+    ) -> rdbc::Result<impl Read + '_> {
+        self.conn
+            .blob_open(DatabaseName::Main, table, column, rowid, true)
+            .map_err(to_rdbc_err)
+    }
+
+    /// Run `PRAGMA name` or `PRAGMA name = value`, returning the first column of the first row
+    /// produced by the pragma, or `None` if it does not produce any rows. Unlike
+    /// `Connection::set_var` this accepts any pragma. The value is passed as a string literal,
+    /// which SQLite accepts for numeric and keyword arguments as well.
+    pub fn pragma(&mut self, name: &str, value: Option<&str>) -> rdbc::Result<Option<rdbc::Value>> {
+        let dialect = rdbc::SqliteDialect;
+        let name = name
+            .split('.')
+            .map(|name| dialect.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(".");
+        let sql = match value {
+            Some(value) => format!("PRAGMA {} = {}", name, dialect.string_literal(value)),
+            None => format!("PRAGMA {}", name),
+        };
+        debug!("Executing pragma: {}", sql);
+        let last_error = &self.last_error;
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| record_err(last_error, e))?;
+        let mut rows = stmt
+            .query(rusqlite::NO_PARAMS)
+            .map_err(|e| record_err(last_error, e))?;
+        match rows.next().map_err(|e| record_err(last_error, e))? {
+            Some(row) => to_rdbc_value(row.get_raw_checked(0).map_err(to_rdbc_err)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Start a bulk load into a table. Rows are inserted with a single prepared statement in one
+    /// transaction, which is much faster than executing a separate `INSERT` for each row. The
+    /// transaction is committed by `Appender::commit`, or when the appender is dropped.
+    pub fn appender(&mut self, table: &str) -> rdbc::Result<Appender<'_>> {
+        let table = table
+            .split('.')
+            .map(|name| rdbc::SqliteDialect.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(".");
+        let columns = self
+            .conn
+            .prepare(&format!("SELECT * FROM {}", table))
+            .map_err(to_rdbc_err)?
+            .column_count();
+        let sql = format!(
+            "INSERT INTO {} VALUES ({})",
+            table,
+            vec!["?"; columns].join(", ")
+        );
+        debug!("Appending rows: {}", sql);
+
+        self.conn.execute_batch("BEGIN").map_err(to_rdbc_err)?;
+        match self.conn.prepare(&sql) {
+            Ok(stmt) => Ok(Appender {
+                conn: &self.conn,
+                stmt: Some(stmt),
+                columns,
+                rows: 0,
+            }),
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(to_rdbc_err(e))
+            }
+        }
+    }
+}
+
+/// Inserts rows into a table in a single transaction, created by `SConnection::appender`. If
+/// the appender is dropped without calling `commit`, the rows are committed and any error is
+/// logged as a warning, since it cannot be returned from `drop`.
+pub struct Appender<'a> {
+    conn: &'a rusqlite::Connection,
+    /// The `INSERT` statement, which is only `None` once the appender has been committed
+    stmt: Option<rusqlite::Statement<'a>>,
+    columns: usize,
+    rows: u64,
+}
+
+impl<'a> Appender<'a> {
+    /// Insert a row, with a value for every column of the table in column order
+    pub fn append_row(&mut self, values: &[rdbc::Value]) -> rdbc::Result<()> {
+        if values.len() != self.columns {
+            return Err(rdbc::Error::General(format!(
+                "Expected {} values per row but found {}",
+                self.columns,
+                values.len()
+            )));
+        }
+        self.stmt
+            .as_mut()
+            .expect("appender has been committed")
+            .execute(values.iter().map(SqliteValue))
+            .map_err(to_rdbc_err)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Commit the transaction, returning the number of rows appended
+    pub fn commit(mut self) -> rdbc::Result<u64> {
+        self.finish()?;
+        Ok(self.rows)
+    }
+
+    fn finish(&mut self) -> rdbc::Result<()> {
+        if let Some(stmt) = self.stmt.take() {
+            let result = stmt
+                .finalize()
+                .and_then(|_| self.conn.execute_batch("COMMIT"));
+            if let Err(e) = result {
+                // don't leave the transaction open on the connection
+                if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+                    warn!("Failed to roll back appended rows: {:?}", e);
+                }
+                return Err(to_rdbc_err(e));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Drop for Appender<'a> {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            warn!("Failed to commit appended rows: {:?}", e);
+        }
     }
 }
 
 impl rdbc::Connection for SConnection {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn dialect(&self) -> &dyn rdbc::Dialect {
+        &rdbc::SqliteDialect
+    }
+
     fn create(&mut self, sql: &str) -> rdbc::Result<Box<dyn rdbc::Statement + '_>> {
         self.prepare(sql)
     }
 
     fn prepare(&mut self, sql: &str) -> rdbc::Result<Box<dyn rdbc::Statement + '_>> {
-        let stmt = self.conn.prepare(sql).map_err(to_rdbc_err)?;
-        Ok(Box::new(SStatement { stmt }))
+        debug!("Preparing statement: {}", sql);
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| record_err(&self.last_error, e).with_query(sql, &[]))?;
+        let params = clear_bindings(&mut stmt);
+        Ok(Box::new(SStatement {
+            conn: &self.conn,
+            stmt: Some(stmt),
+            params,
+            sql: sql.to_owned(),
+            arrays: self.arrays,
+            max_rows: None,
+            last_error: &self.last_error,
+        }))
+    }
+
+    fn set_read_only(&mut self, on: bool) -> rdbc::Result<()> {
+        self.conn
+            .execute_batch(&format!("PRAGMA query_only = {}", on))
+            .map_err(to_rdbc_err)
+    }
+
+    /// Set a pragma with `PRAGMA name = value`. Only pragmas that configure the connection
+    /// are supported: `busy_timeout`, `cache_size`, `case_sensitive_like`, `foreign_keys`,
+    /// `journal_mode`, `recursive_triggers`, `synchronous` and `temp_store`.
+    fn set_var(&mut self, name: &str, value: rdbc::Value) -> rdbc::Result<()> {
+        if !SESSION_PRAGMAS.contains(&name.to_lowercase().as_str()) {
+            return Err(rdbc::Error::NotSupported(format!(
+                "Setting the {} pragma is not supported",
+                name
+            )));
+        }
+        let dialect = rdbc::SqliteDialect;
+        self.conn
+            .execute_batch(&format!(
+                "PRAGMA {} = {}",
+                dialect.quote_identifier(name),
+                value.to_sql_literal(&dialect)
+            ))
+            .map_err(to_rdbc_err)
+    }
+
+    /// Cancellation uses `sqlite3_interrupt`, so the query fails with an "interrupted" error
+    fn cancel_token(&self) -> rdbc::Result<rdbc::CancelToken> {
+        let handle = self.conn.get_interrupt_handle();
+        Ok(rdbc::CancelToken::new(move || {
+            handle.interrupt();
+            Ok(())
+        }))
+    }
+
+    fn last_error(&self) -> Option<rdbc::DbError> {
+        self.last_error.borrow().clone()
+    }
+
+    fn tables(&mut self) -> rdbc::Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT name FROM sqlite_master \
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )
+            .map_err(to_rdbc_err)?;
+        let tables = stmt
+            .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+            .and_then(|tables| tables.collect::<rusqlite::Result<Vec<String>>>())
+            .map_err(to_rdbc_err)?;
+        Ok(tables)
+    }
+
+    fn columns(&mut self, table: &str) -> rdbc::Result<Box<dyn rdbc::ResultSetMetaData>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, type, dflt_value FROM pragma_table_info(?) ORDER BY cid")
+            .map_err(to_rdbc_err)?;
+        let columns = stmt
+            .query_map(&[table], |row| {
+                let name: String = row.get(0)?;
+                let data_type: String = row.get(1)?;
+                let default: Option<String> = row.get(2)?;
+                Ok(rdbc::Column::new(&name, to_rdbc_type(Some(&data_type)))
+                    .with_default(default)
+                    .with_type_name(Some(data_type)))
+            })
+            .and_then(|columns| columns.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(to_rdbc_err)?;
+        Ok(Box::new(columns))
     }
 }
 
+/// Pragmas that can be set with `Connection::set_var`
+const SESSION_PRAGMAS: &[&str] = &[
+    "busy_timeout",
+    "cache_size",
+    "case_sensitive_like",
+    "foreign_keys",
+    "journal_mode",
+    "recursive_triggers",
+    "synchronous",
+    "temp_store",
+];
+
+/// A prepared statement. The statement is finalized when it is dropped and any error reported by
+/// SQLite is logged as a warning, since it cannot be returned from `drop`.
 struct SStatement<'a> {
-    stmt: rusqlite::Statement<'a>,
+    conn: &'a rusqlite::Connection,
+    /// The underlying statement, which is only `None` while it is being finalized
+    stmt: Option<rusqlite::Statement<'a>>,
+    /// The number of parameters in the SQL
+    params: usize,
+    sql: String,
+    arrays: bool,
+    /// Maximum number of rows returned by `execute_query`
+    max_rows: Option<u64>,
+    last_error: &'a RefCell<Option<rdbc::DbError>>,
+}
+
+impl<'a> SStatement<'a> {
+    fn stmt(&mut self) -> &mut rusqlite::Statement<'a> {
+        self.stmt.as_mut().expect("statement has been finalized")
+    }
+
+    fn check_params(&self, params: &[rdbc::Value]) -> rdbc::Result<()> {
+        // rusqlite panics if the number of parameters is wrong
+        if params.len() != self.params {
+            return Err(rdbc::Error::General(format!(
+                "the statement has {} parameter(s) but {} value(s) were given",
+                self.params,
+                params.len()
+            )));
+        }
+        let has_array = params.iter().any(|p| match p {
+            rdbc::Value::Array(_) => true,
+            _ => false,
+        });
+        if has_array && !self.arrays {
+            return Err(rdbc::Error::NotSupported(
+                "Array parameters require the SQLite carray extension".to_owned(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl<'a> rdbc::Statement for SStatement<'a> {
@@ -71,18 +509,121 @@ impl<'a> rdbc::Statement for SStatement<'a> {
         &mut self,
         params: &[rdbc::Value],
     ) -> rdbc::Result<Box<dyn rdbc::ResultSet + '_>> {
-        let params = Values(params);
-        let rows = self.stmt.query(&params).map_err(to_rdbc_err)?;
-        Ok(Box::new(SResultSet { rows }))
+        // statements such as VACUUM only run when they are stepped, so run them now rather than
+        // returning a result set that may never be read
+        if !self.returns_rows() {
+            let rows = self.execute_update(params)?;
+            return Ok(Box::new(
+                rdbc::MaterializedResultSet::new(vec![], vec![]).with_rows_affected(Some(rows)),
+            ));
+        }
+        debug!(
+            "Executing query: {} {}",
+            self.sql,
+            rdbc::redact_params(params)
+        );
+        self.check_params(params)?;
+        let max_rows = self.max_rows;
+        let last_error = self.last_error;
+        // borrow the statement and its SQL separately so that the SQL can be added to errors
+        let sql = &self.sql;
+        let rows = self
+            .stmt
+            .as_mut()
+            .expect("statement has been finalized")
+            .query(params.iter().map(SqliteValue))
+            .map_err(|e| record_err(last_error, e).with_query(sql, params))?;
+        Ok(rdbc::LimitedResultSet::wrap(
+            Box::new(SResultSet { rows, position: 0 }),
+            max_rows,
+        ))
     }
 
     fn execute_update(&mut self, params: &[rdbc::Value]) -> rdbc::Result<u64> {
-        let params = Values(params);
-        return self
-            .stmt
-            .execute(&params)
-            .map_err(to_rdbc_err)
-            .map(|n| n as u64);
+        debug!(
+            "Executing update: {} {}",
+            self.sql,
+            rdbc::redact_params(params)
+        );
+        self.check_params(params)?;
+        let last_error = self.last_error;
+        let result = self
+            .stmt()
+            .execute(params.iter().map(SqliteValue))
+            .map_err(|e| record_err(last_error, e));
+        result
+            .map(|n| n as u64)
+            .map_err(|e| e.with_query(&self.sql, params))
+    }
+
+    /// Returns the rowids of the inserted rows, which are the values of an `INTEGER PRIMARY KEY`
+    /// column if the table has one. SQLite only reports the rowid of the last row inserted, so
+    /// when a statement inserts several rows the others are derived from it and the number of
+    /// rows inserted. This assumes that the rows were given consecutive rowids, which SQLite
+    /// does unless the statement specifies them.
+    fn execute_returning_keys(&mut self, params: &[rdbc::Value]) -> rdbc::Result<Vec<rdbc::Value>> {
+        debug!(
+            "Executing insert: {} {}",
+            self.sql,
+            rdbc::redact_params(params)
+        );
+        self.check_params(params)?;
+        let (conn, last_error) = (self.conn, self.last_error);
+        let result = self
+            .stmt()
+            .execute(params.iter().map(SqliteValue))
+            .map_err(|e| record_err(last_error, e));
+        result
+            .map(|rows| {
+                let last = conn.last_insert_rowid();
+                (last - rows as i64 + 1..=last)
+                    .map(rdbc::Value::Int64)
+                    .collect()
+            })
+            .map_err(|e| e.with_query(&self.sql, params))
+    }
+
+    fn rewritten_sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// SQLite knows how many columns a prepared statement returns, which is more reliable than
+    /// the leading keyword for statements such as `PRAGMA user_version = 5`
+    fn returns_rows(&self) -> bool {
+        self.stmt
+            .as_ref()
+            .map_or(false, |stmt| stmt.column_count() > 0)
+    }
+
+    fn set_max_rows(&mut self, max_rows: u64) -> rdbc::Result<()> {
+        self.max_rows = Some(max_rows);
+        Ok(())
+    }
+
+    /// SQLite keeps the values bound to a statement until they are replaced, so this binds
+    /// `NULL` to every parameter. The statement itself is reset when its result set is dropped.
+    fn reset(&mut self) -> rdbc::Result<()> {
+        clear_bindings(self.stmt());
+        Ok(())
+    }
+}
+
+/// Bind `NULL` to every parameter of a statement, as `sqlite3_clear_bindings` does, returning
+/// the number of parameters. rusqlite doesn't expose either function, so this binds parameters
+/// in turn until SQLite reports that the index is out of range.
+fn clear_bindings(stmt: &mut rusqlite::Statement<'_>) -> usize {
+    (1..)
+        .take_while(|i| stmt.raw_bind_parameter(*i, Null).is_ok())
+        .count()
+}
+
+impl<'a> Drop for SStatement<'a> {
+    fn drop(&mut self) {
+        if let Some(stmt) = self.stmt.take() {
+            if let Err(e) = stmt.finalize() {
+                warn!("Failed to finalize statement '{}': {:?}", self.sql, e);
+            }
+        }
     }
 }
 
@@ -90,11 +631,7 @@ macro_rules! impl_resultset_fns {
     ($($fn: ident -> $ty: ty),*) => {
         $(
             fn $fn(&self, i: u64) -> rdbc::Result<Option<$ty>> {
-                self.rows
-                    .get()
-                    .unwrap()
-                    .get(i as usize)
-                    .map_err(to_rdbc_err)
+                get_value(self.rows.get().unwrap(), i)
             }
         )*
     }
@@ -102,6 +639,8 @@ macro_rules! impl_resultset_fns {
 
 struct SResultSet<'stmt> {
     rows: Rows<'stmt>,
+    /// Number of rows read so far
+    position: u64,
 }
 
 impl<'stmt> rdbc::ResultSet for SResultSet<'stmt> {
@@ -111,17 +650,30 @@ impl<'stmt> rdbc::ResultSet for SResultSet<'stmt> {
             .columns()
             .unwrap()
             .iter()
-            .map(|c| rdbc::Column::new(c.name(), to_rdbc_type(c.decl_type())))
+            .map(|c| {
+                rdbc::Column::new(c.name(), to_rdbc_type(c.decl_type()))
+                    .with_type_name(c.decl_type().map(str::to_owned))
+            })
             .collect();
         Ok(Box::new(meta))
     }
 
-    fn next(&mut self) -> bool {
-        self.rows.next().unwrap().is_some()
+    fn next(&mut self) -> rdbc::Result<bool> {
+        let has_row = self.rows.next().map_err(to_rdbc_err)?.is_some();
+        if has_row {
+            self.position += 1;
+        }
+        Ok(has_row)
+    }
+
+    fn position(&self) -> u64 {
+        self.position
     }
 
-    fn get_f32(&self, _i: u64) -> rdbc::Result<Option<f32>> {
-        Err(rdbc::Error::General("f32 not supported".to_owned()))
+    /// SQLite stores `REAL` values as 64-bit floats, so they are read as `f64` and narrowed to
+    /// `f32`. This loses precision, and values outside the range of `f32` become infinite.
+    fn get_f32(&self, i: u64) -> rdbc::Result<Option<f32>> {
+        Ok(self.get_f64(i)?.map(|n| n as f32))
     }
 
     impl_resultset_fns! {
@@ -129,40 +681,187 @@ impl<'stmt> rdbc::ResultSet for SResultSet<'stmt> {
         get_i16 -> i16,
         get_i32 -> i32,
         get_i64 -> i64,
-        get_f64 -> f64,
-        get_string -> String,
-        get_bytes -> Vec<u8>
+        get_f64 -> f64
     }
-}
 
-fn to_rdbc_type(t: Option<&str>) -> rdbc::DataType {
-    //TODO implement for real
-    match t {
-        Some("INT") => rdbc::DataType::Integer,
-        _ => rdbc::DataType::Utf8,
+    fn get_string(&self, i: u64) -> rdbc::Result<Option<String>> {
+        let row = self.rows.get().unwrap();
+        match row.get_raw_checked(i as usize).map_err(to_rdbc_err)? {
+            ValueRef::Text(t) => Ok(Some(std::str::from_utf8(t)?.to_owned())),
+            _ => get_value(row, i),
+        }
+    }
+
+    fn is_null(&self, i: u64) -> rdbc::Result<bool> {
+        let row = self.rows.get().unwrap();
+        match row.get_raw_checked(i as usize).map_err(to_rdbc_err)? {
+            ValueRef::Null => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    fn get_bytes(&self, i: u64) -> rdbc::Result<Option<Vec<u8>>> {
+        let value = self
+            .rows
+            .get()
+            .unwrap()
+            .get_raw_checked(i as usize)
+            .map_err(to_rdbc_err)?;
+        match value {
+            ValueRef::Null => Ok(None),
+            ValueRef::Blob(b) | ValueRef::Text(b) => Ok(Some(b.to_vec())),
+            _ => Err(type_mismatch::<Vec<u8>>(value)),
+        }
+    }
+
+    /// The reader borrows the value from SQLite instead of copying it, but SQLite has already
+    /// read the whole value into memory along with the row. Use `SConnection::blob_reader` to
+    /// read a blob incrementally given its table, column and rowid.
+    fn get_blob_reader(&self, i: u64) -> rdbc::Result<Option<Box<dyn Read + '_>>> {
+        let value = self
+            .rows
+            .get()
+            .unwrap()
+            .get_raw_checked(i as usize)
+            .map_err(to_rdbc_err)?;
+        match value {
+            ValueRef::Null => Ok(None),
+            ValueRef::Blob(b) | ValueRef::Text(b) => Ok(Some(Box::new(b))),
+            _ => Err(type_mismatch::<Vec<u8>>(value)),
+        }
+    }
+
+    fn get_display(&self, i: u64) -> rdbc::Result<String> {
+        let value = self
+            .rows
+            .get()
+            .unwrap()
+            .get_raw_checked(i as usize)
+            .map_err(to_rdbc_err)?;
+        to_rdbc_value(value).map(|v| v.to_display_string())
     }
 }
 
-struct Values<'a>(&'a [rdbc::Value]);
-struct ValuesIter<'a>(std::slice::Iter<'a, rdbc::Value>);
+/// Convert a SQLite value into the RDBC value for its storage class
+fn to_rdbc_value(value: ValueRef) -> rdbc::Result<rdbc::Value> {
+    Ok(match value {
+        ValueRef::Null => rdbc::Value::Null,
+        ValueRef::Integer(n) => rdbc::Value::Int64(n),
+        ValueRef::Real(n) => rdbc::Value::Float64(n),
+        ValueRef::Text(_) => rdbc::Value::String(
+            value
+                .as_str()
+                .map_err(|_| type_mismatch::<String>(value))?
+                .to_owned(),
+        ),
+        ValueRef::Blob(b) => rdbc::Value::Bytes(b.to_vec()),
+    })
+}
+
+/// SQLite columns are dynamically typed, so the value stored in a cell does not necessarily
+/// match the declared type of its column. The requested conversion is attempted first and,
+/// if the stored type doesn't match, we fall back to parsing the textual form of the value.
+fn get_value<T>(row: &rusqlite::Row, i: u64) -> rdbc::Result<Option<T>>
+where
+    T: FromSql + FromStr,
+{
+    let value = row.get_raw_checked(i as usize).map_err(to_rdbc_err)?;
+    match T::column_result(value) {
+        Ok(v) => Ok(Some(v)),
+        Err(FromSqlError::InvalidType) => {
+            let text = match value {
+                ValueRef::Null => return Ok(None),
+                ValueRef::Integer(n) => n.to_string(),
+                ValueRef::Real(n) => n.to_string(),
+                ValueRef::Text(_) => value
+                    .as_str()
+                    .map_err(|_| type_mismatch::<T>(value))?
+                    .trim()
+                    .to_owned(),
+                ValueRef::Blob(_) => return Err(type_mismatch::<T>(value)),
+            };
+            text.parse::<T>()
+                .map(Some)
+                .map_err(|_| type_mismatch::<T>(value))
+        }
+        Err(e) => Err(rdbc::Error::General(format!("{:?}", e))),
+    }
+}
 
-impl<'a> IntoIterator for &'a Values<'a> {
-    type IntoIter = ValuesIter<'a>;
-    type Item = &'a dyn rusqlite::types::ToSql;
+/// Create an error describing the type actually stored in a cell that could not be converted
+fn type_mismatch<T>(value: ValueRef) -> rdbc::Error {
+    rdbc::Error::General(format!(
+        "Cannot convert SQLite {} value to {}",
+        value.data_type(),
+        std::any::type_name::<T>()
+    ))
+}
 
-    fn into_iter(self) -> ValuesIter<'a> {
-        ValuesIter(self.0.iter())
+/// Map a declared column type to an RDBC type. Following SQLite's affinity rules, any type whose
+/// name contains "INT", ignoring case, is an integer type. SQLite stores integers in up to 64
+/// bits, so these are read as `Long`, except for `INT` itself, which is read as `Integer`.
+fn to_rdbc_type(t: Option<&str>) -> rdbc::DataType {
+    let t = t.unwrap_or("").to_uppercase();
+    // ignore any size, as in `INT(11)`
+    let name = t.split('(').next().unwrap_or("").trim();
+    if name == "INT" {
+        rdbc::DataType::Integer
+    } else if name.contains("INT") {
+        rdbc::DataType::Long
+    } else {
+        rdbc::DataType::Utf8
     }
 }
-impl<'a> Iterator for ValuesIter<'a> {
-    type Item = &'a dyn rusqlite::types::ToSql;
 
-    fn next(&mut self) -> Option<&'a dyn rusqlite::types::ToSql> {
-        self.0.next().map(|v| match v {
-            rdbc::Value::String(ref s) => s as &dyn rusqlite::types::ToSql,
-            rdbc::Value::Int32(ref n) => n as &dyn rusqlite::types::ToSql,
-            rdbc::Value::UInt32(ref n) => n as &dyn rusqlite::types::ToSql,
-        })
+/// Wrapper used to bind an RDBC value as a SQLite parameter
+struct SqliteValue<'a>(&'a rdbc::Value);
+
+impl<'a> ToSql for SqliteValue<'a> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        use rusqlite::types::Value;
+        match self.0 {
+            rdbc::Value::Null => Ok(ToSqlOutput::Owned(Value::Null)),
+            rdbc::Value::Bool(b) => Ok(ToSqlOutput::Owned(Value::Integer(*b as i64))),
+            rdbc::Value::Int8(n) => Ok(ToSqlOutput::Owned(Value::Integer(i64::from(*n)))),
+            rdbc::Value::Int16(n) => Ok(ToSqlOutput::Owned(Value::Integer(i64::from(*n)))),
+            rdbc::Value::Int32(n) => Ok(ToSqlOutput::Owned(Value::Integer(i64::from(*n)))),
+            rdbc::Value::Int64(n) => Ok(ToSqlOutput::Owned(Value::Integer(*n))),
+            rdbc::Value::UInt32(n) => Ok(ToSqlOutput::Owned(Value::Integer(i64::from(*n)))),
+            rdbc::Value::Float32(n) => Ok(ToSqlOutput::Owned(Value::Real(f64::from(*n)))),
+            rdbc::Value::Float64(n) => Ok(ToSqlOutput::Owned(Value::Real(*n))),
+            rdbc::Value::String(s) => s.to_sql(),
+            rdbc::Value::Char(c) => Ok(ToSqlOutput::from(c.to_string())),
+            rdbc::Value::Bytes(b) => b.to_sql(),
+            rdbc::Value::Interval(_) => Err(rusqlite::Error::ToSqlConversionFailure(
+                "Interval parameters are not supported by SQLite".into(),
+            )),
+            rdbc::Value::Record(_) => Err(rusqlite::Error::ToSqlConversionFailure(
+                "Record parameters are not supported by SQLite".into(),
+            )),
+            rdbc::Value::Range { .. } => Err(rusqlite::Error::ToSqlConversionFailure(
+                "Range parameters are not supported by SQLite".into(),
+            )),
+            // SQLite has no decimal type, so decimals are bound as text to preserve their
+            // precision when stored in a column with TEXT affinity
+            #[cfg(feature = "bigdecimal")]
+            rdbc::Value::Decimal(d) => Ok(ToSqlOutput::from(d.to_string())),
+            rdbc::Value::Array(values) => {
+                let values = values
+                    .iter()
+                    .map(|v| match SqliteValue(v).to_sql()? {
+                        ToSqlOutput::Borrowed(v) => Ok(v.into()),
+                        ToSqlOutput::Owned(v) => Ok(v),
+                        _ => Err(rusqlite::Error::ToSqlConversionFailure(
+                            "Arrays may only contain scalar values".into(),
+                        )),
+                    })
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(ToSqlOutput::Array(Rc::new(values)))
+            }
+            v => Err(rusqlite::Error::ToSqlConversionFailure(
+                format!("Unsupported parameter: {:?}", v).into(),
+            )),
+        }
     }
 }
 
@@ -192,14 +891,1272 @@ mod tests {
         assert_eq!(1, meta.num_columns());
         assert_eq!("a".to_owned(), meta.column_name(0));
         assert_eq!(DataType::Integer, meta.column_type(0));
+        assert_eq!(Some("INT".to_owned()), meta.column_type_name(0));
+
+        assert!(rs.next()?);
+        assert_eq!(Some(123), rs.get_i32(0)?);
+        assert!(!rs.next()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dynamic_typing() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INTEGER, b)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (?, ?)",
+            &vec![
+                rdbc::Value::String("abc".to_owned()),
+                rdbc::Value::String("42".to_owned()),
+            ],
+        )?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (?, ?)",
+            &vec![rdbc::Value::Int32(123), rdbc::Value::Int32(7)],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a, b FROM test ORDER BY rowid")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+
+        // text stored in an INTEGER column
+        assert!(rs.next()?);
+        match rs.get_i32(0) {
+            Err(rdbc::Error::General(msg)) => assert!(msg.contains("Text"), msg),
+            other => panic!("expected type mismatch error, got {:?}", other),
+        }
+        assert_eq!(Some("abc".to_owned()), rs.get_string(0)?);
+        // numeric text is parsed on a best-effort basis
+        assert_eq!(Some(42), rs.get_i32(1)?);
+        assert_eq!(Some(42), rs.get_i64(1)?);
+
+        // integers can be read as strings
+        assert!(rs.next()?);
+        assert_eq!(Some("123".to_owned()), rs.get_string(0)?);
+        assert_eq!(Some(7), rs.get_i8(1)?);
+        assert!(!rs.next()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_row_values() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (a INT, b TEXT, c INT)",
+            &vec![],
+        )?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b, c) VALUES (?, ?, ?)",
+            &vec![
+                rdbc::Value::Int32(1),
+                rdbc::Value::String("one".to_owned()),
+                rdbc::Value::Null,
+            ],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a, b, c FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        assert_eq!(
+            vec![
+                rdbc::Value::Int32(1),
+                rdbc::Value::String("one".to_owned()),
+                rdbc::Value::Null
+            ],
+            rs.get_row_values()?
+        );
+        assert!(!rs.next()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_cache() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let url = "file:rdbc_shared_cache?mode=memory&cache=shared";
+        let mut conn1 = driver.connect(url)?;
+        let mut conn2 = driver.connect(url)?;
+
+        execute(&mut *conn1, "CREATE TABLE test (a INT)", &vec![])?;
+        execute(
+            &mut *conn1,
+            "INSERT INTO test (a) VALUES (?)",
+            &vec![rdbc::Value::Int32(123)],
+        )?;
 
-        assert!(rs.next());
+        let mut stmt = conn2.prepare("SELECT a FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
         assert_eq!(Some(123), rs.get_i32(0)?);
-        assert!(!rs.next());
 
         Ok(())
     }
 
+    #[test]
+    fn journal_mode() -> rdbc::Result<()> {
+        let path = std::env::temp_dir().join("rdbc_journal_mode.db");
+        let _ = std::fs::remove_file(&path);
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect(&format!("{}?journal_mode=WAL", path.display()))?;
+
+        let mut stmt = conn.prepare("PRAGMA journal_mode")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        assert_eq!(Some("wal".to_owned()), rs.get_string(0)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_only() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        conn.set_read_only(true)?;
+        match execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![]) {
+            Err(rdbc::Error::Query { source, .. }) => assert!(source.contains("readonly"), source),
+            other => panic!("expected read-only error, got {:?}", other),
+        }
+        conn.set_read_only(false)?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+        Ok(())
+    }
+
+    #[test]
+    fn read_only_url() -> rdbc::Result<()> {
+        let path = std::env::temp_dir().join("rdbc_read_only.db");
+        let _ = std::fs::remove_file(&path);
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect(&format!("{}", path.display()))?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+
+        let mut conn = driver.connect(&format!("{}?read_only=true", path.display()))?;
+        assert!(execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn into_owned() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (1, 'one'), (2, NULL)",
+            &vec![],
+        )?;
+
+        let rs = {
+            let mut stmt = conn.prepare("SELECT a, b FROM test ORDER BY a")?;
+            let rs = stmt.execute_query(&vec![])?;
+            rs.into_owned()?
+        };
+        drop(conn);
+
+        let rows = std::thread::spawn(move || -> rdbc::Result<Vec<Vec<rdbc::Value>>> {
+            let mut rs: Box<dyn rdbc::ResultSet> = Box::new(rs);
+            assert_eq!("b", rs.meta_data()?.column_name(1));
+            let mut rows = vec![];
+            while let Some(row) = rs.next_row()? {
+                rows.push(row.get_row_values()?);
+            }
+            Ok(rows)
+        })
+        .join()
+        .unwrap()?;
+        assert_eq!(
+            vec![
+                vec![rdbc::Value::Int32(1), rdbc::Value::from("one")],
+                vec![rdbc::Value::Int32(2), rdbc::Value::Null]
+            ],
+            rows
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reset_statement() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1), (2)", &vec![])?;
+
+        let mut stmt = conn.prepare("SELECT a, ? FROM test WHERE a <= ? ORDER BY a")?;
+        {
+            let params = [rdbc::Value::from("x"), rdbc::Value::Int32(2)];
+            let mut rs = stmt.execute_query(&params)?;
+            assert!(rs.next()?);
+            assert_eq!(Some("x".to_owned()), rs.get_string(1)?);
+        }
+        stmt.reset()?;
+
+        // the previous bindings must not be reused for the missing parameters
+        assert!(stmt.execute_query(&[]).is_err());
+        assert!(stmt.execute_update(&[rdbc::Value::Null]).is_err());
+
+        // the cursor starts from the first row and only the new bindings are used
+        let params = [rdbc::Value::Null, rdbc::Value::Int32(1)];
+        let mut rs = stmt.execute_query(&params)?;
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        assert_eq!(None, rs.get_string(1)?);
+        assert!(!rs.next()?);
+        Ok(())
+    }
+
+    #[test]
+    fn cleared_bindings() -> rdbc::Result<()> {
+        let conn = rusqlite::Connection::open_in_memory().map_err(to_rdbc_err)?;
+        conn.execute_batch("CREATE TABLE test (a, b, c)")
+            .map_err(to_rdbc_err)?;
+        let mut stmt = conn
+            .prepare("INSERT INTO test (a, b, c) VALUES (?, :b, ?5)")
+            .map_err(to_rdbc_err)?;
+        stmt.raw_bind_parameter(1, 1).map_err(to_rdbc_err)?;
+        assert_eq!(5, clear_bindings(&mut stmt));
+        stmt.raw_execute().map_err(to_rdbc_err)?;
+        drop(stmt);
+
+        let value: Option<i32> = conn
+            .query_row("SELECT a FROM test", rusqlite::NO_PARAMS, |row| row.get(0))
+            .map_err(to_rdbc_err)?;
+        assert_eq!(None, value);
+        Ok(())
+    }
+
+    #[test]
+    fn query_map() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (1, 'one'), (2, 'two')",
+            &vec![],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a, b FROM test ORDER BY a")?;
+        let rows = stmt.query_map(&[], |row| Ok((row.get_i32(0)?, row.get_string(1)?)))?;
+        assert_eq!(
+            vec![
+                (Some(1), Some("one".to_owned())),
+                (Some(2), Some("two".to_owned()))
+            ],
+            rows
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn insert_many() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+
+        let rows: Vec<Vec<rdbc::Value>> = (0..50)
+            .map(|i| vec![rdbc::Value::Int32(i), rdbc::Value::String(i.to_string())])
+            .collect();
+        assert_eq!(50, conn.insert_many("test", &["a", "b"], &rows)?);
+
+        // enough rows to exceed the parameter limit for a single statement
+        let rows: Vec<Vec<rdbc::Value>> = (0..600)
+            .map(|i| vec![rdbc::Value::Int32(i), rdbc::Value::Null])
+            .collect();
+        assert_eq!(600, conn.insert_many("test", &["a", "b"], &rows)?);
+
+        let mut stmt = conn.prepare("SELECT COUNT(*) FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(650), rs.get_i32(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn downcast_native_connection() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+
+        let sconn = conn
+            .as_any()
+            .downcast_ref::<SConnection>()
+            .expect("expected an SConnection");
+        assert_eq!(1, sconn.raw().last_insert_rowid());
+        assert!(sconn.raw().is_autocommit());
+        Ok(())
+    }
+
+    /// Logger that records the messages logged by the current thread
+    struct TestLogger;
+
+    thread_local! {
+        static LOGGED: std::cell::RefCell<Vec<String>> = std::cell::RefCell::new(vec![]);
+    }
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOGGED.with(|logged| logged.borrow_mut().push(record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn log_statements() -> rdbc::Result<()> {
+        let _ = log::set_logger(&TestLogger);
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (?, ?)",
+            &vec![
+                rdbc::Value::Int32(1),
+                rdbc::Value::String("secret".to_owned()),
+            ],
+        )?;
+
+        let logged = LOGGED.with(|logged| logged.borrow().clone());
+        assert!(logged
+            .contains(&"Preparing statement: INSERT INTO test (a, b) VALUES (?, ?)".to_owned()));
+        assert!(logged.contains(
+            &"Executing update: INSERT INTO test (a, b) VALUES (?, ?) [1, <6 chars>]".to_owned()
+        ));
+        assert!(!logged.iter().any(|line| line.contains("secret")));
+        Ok(())
+    }
+
+    #[test]
+    fn get_f32() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a REAL)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (1.1), (NULL)",
+            &vec![],
+        )?;
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        let n = rs.get_f32(0)?.unwrap();
+        assert!((n - 1.1).abs() < std::f32::EPSILON);
+        assert!(rs.next()?);
+        assert_eq!(None, rs.get_f32(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn json_path() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (doc TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            r#"INSERT INTO test (doc) VALUES ('{"k": 42}'), (NULL)"#,
+            &vec![],
+        )?;
+        let mut stmt = conn.prepare("SELECT doc FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(rdbc::Value::Int32(42)), rs.get_json_path(0, "$.k")?);
+        assert_eq!(None, rs.get_json_path(0, "$.missing")?);
+        assert!(rs.next()?);
+        assert_eq!(None, rs.get_json_path(0, "$.k")?);
+        Ok(())
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn big_decimal() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a TEXT)", &vec![])?;
+        let d: rdbc::BigDecimal = "1234567890123456789012345678901234.567890".parse().unwrap();
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (?)",
+            &vec![rdbc::Value::from(d.clone())],
+        )?;
+        let mut rs = conn.query("SELECT a FROM test", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(d), rs.get_big_decimal(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn column_aliases() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        let rs = conn.query("SELECT count(*) AS total, count(*) FROM test", &[])?;
+        let meta = rs.meta_data()?;
+        assert_eq!("total", meta.column_name(0));
+        assert_eq!("count(*)", meta.column_name(1));
+        Ok(())
+    }
+
+    #[test]
+    fn columns() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        let rs = conn.query("SELECT b, a AS x FROM test", &[])?;
+        let expected = vec![
+            rdbc::Column::new("b", rdbc::DataType::Utf8).with_type_name(Some("TEXT".to_owned())),
+            rdbc::Column::new("x", rdbc::DataType::Integer).with_type_name(Some("INT".to_owned())),
+        ];
+        assert_eq!(expected, rs.columns()?);
+        let mut names = vec![];
+        for col in rs.columns()? {
+            names.push(col.name().to_owned());
+        }
+        assert_eq!(vec!["b", "x"], names);
+        Ok(())
+    }
+
+    #[test]
+    fn appender() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        let sconn = conn.as_any_mut().downcast_mut::<SConnection>().unwrap();
+
+        let mut appender = sconn.appender("test")?;
+        for i in 0..10_000 {
+            appender.append_row(&[rdbc::Value::Int32(i), format!("row {}", i).into()])?;
+        }
+        assert!(appender.append_row(&[rdbc::Value::Int32(0)]).is_err());
+        assert_eq!(10_000, appender.commit()?);
+
+        // dropping the appender also commits the rows
+        sconn
+            .appender("test")?
+            .append_row(&[rdbc::Value::Int32(-1), rdbc::Value::Null])?;
+
+        let mut rs = conn.query("SELECT COUNT(*), SUM(a) FROM test", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(10_001), rs.get_i64(0)?);
+        assert_eq!(Some(49_995_000 - 1), rs.get_i64(1)?);
+        Ok(())
+    }
+
+    /// Compare the appender with executing a separate INSERT for each row, using a database file
+    /// so that each autocommitted INSERT has to be synced to disk
+    #[test]
+    #[ignore]
+    fn appender_performance() -> rdbc::Result<()> {
+        let path = std::env::temp_dir().join("rdbc_appender.db");
+        let _ = std::fs::remove_file(&path);
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect(path.to_str().unwrap())?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        let rows = 1_000;
+
+        let start = std::time::Instant::now();
+        for i in 0..rows {
+            execute(
+                &mut *conn,
+                "INSERT INTO test VALUES (?)",
+                &vec![rdbc::Value::Int32(i)],
+            )?;
+        }
+        let naive = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let sconn = conn.as_any_mut().downcast_mut::<SConnection>().unwrap();
+        let mut appender = sconn.appender("test")?;
+        for i in 0..rows {
+            appender.append_row(&[rdbc::Value::Int32(i)])?;
+        }
+        appender.commit()?;
+        let appended = start.elapsed();
+
+        println!("naive: {:?}, appender: {:?}", naive, appended);
+        assert!(appended * 10 < naive);
+        Ok(())
+    }
+
+    #[test]
+    fn get_value_by_name() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (id BIGINT, name TEXT)",
+            &vec![],
+        )?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (id, name) VALUES (1, 'one')",
+            &vec![],
+        )?;
+        let mut rs = conn.query("SELECT id, name FROM test", &[])?;
+        let row = rs.next_row()?.unwrap();
+        assert_eq!(rdbc::Value::Int64(1), row.get_value_by_name("id")?);
+        assert_eq!(
+            rdbc::Value::String("one".to_owned()),
+            row.get_value_by_name("name")?
+        );
+        assert!(row.get_value_by_name("missing").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn row_to_map() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (id BIGINT, name TEXT)",
+            &vec![],
+        )?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (id, name) VALUES (1, 'one')",
+            &vec![],
+        )?;
+        let mut rs = conn.query("SELECT id, name FROM test", &[])?;
+        let map = rs.next_row()?.unwrap().to_map()?;
+        assert_eq!(2, map.len());
+        assert_eq!(Some(&rdbc::Value::Int64(1)), map.get("id"));
+        assert_eq!(
+            Some(&rdbc::Value::String("one".to_owned())),
+            map.get("name")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pragma() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let sconn = conn.as_any_mut().downcast_mut::<SConnection>().unwrap();
+        assert_eq!(None, sconn.pragma("user_version", Some("5"))?);
+        assert_eq!(
+            Some(rdbc::Value::Int64(5)),
+            sconn.pragma("user_version", None)?
+        );
+        assert_eq!(
+            Some(rdbc::Value::Int64(5)),
+            sconn.pragma("main.user_version", None)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn statements_without_rows() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        assert!(!conn.create("PRAGMA user_version = 7")?.returns_rows());
+        assert!(conn.create("PRAGMA user_version")?.returns_rows());
+        assert!(!conn.create("VACUUM")?.returns_rows());
+
+        // the pragma runs even though the result set is never read
+        conn.create("PRAGMA user_version = 7")?.execute_query(&[])?;
+        let mut rs = conn.query("PRAGMA user_version", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(7), rs.get_i32(0)?);
+        drop(rs);
+
+        conn.execute("ANALYZE", &[])?;
+        conn.execute("VACUUM", &[])?;
+        Ok(())
+    }
+
+    #[test]
+    fn bind_char() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a CHAR(1))", &vec![])?;
+        conn.execute("INSERT INTO test (a) VALUES (?)", &[rdbc::Value::Char('A')])?;
+        let mut rs = conn.query("SELECT a FROM test", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some('A'), rs.get_char(0)?);
+        assert_eq!(Some("A".to_owned()), rs.get_string(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn last_error() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        assert_eq!(None, conn.last_error());
+        execute(&mut *conn, "CREATE TABLE test (a INT UNIQUE)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+        assert!(execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![]).is_err());
+        let err = conn.last_error().unwrap();
+        // SQLITE_CONSTRAINT_UNIQUE
+        assert_eq!("2067", err.code);
+        assert!(err.message.contains("UNIQUE"), err.message);
+        Ok(())
+    }
+
+    #[test]
+    fn query_error() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT UNIQUE)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+
+        let e = conn
+            .execute("INSERT INTO test (a) VALUES (?)", &[rdbc::Value::Int32(1)])
+            .unwrap_err();
+        let msg = e.to_string();
+        assert!(
+            msg.starts_with("error executing 'INSERT INTO test (a) VALUES (?) [1]': "),
+            msg
+        );
+        assert!(msg.contains("UNIQUE"), msg);
+
+        match conn.query("SELECT b FROM test", &[]).err() {
+            Some(rdbc::Error::Query { sql, source }) => {
+                assert_eq!("SELECT b FROM test", sql);
+                assert!(source.contains("no such column"), source);
+            }
+            other => panic!("expected query error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn migrate() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let migrator = rdbc::Migrator::new(vec![
+            (1, "CREATE TABLE users (id INT PRIMARY KEY, name TEXT)"),
+            (
+                2,
+                "ALTER TABLE users ADD COLUMN email TEXT; INSERT INTO users VALUES (1, 'a', 'b')",
+            ),
+        ]);
+        assert_eq!(vec![1, 2], migrator.run(&mut *conn)?);
+        assert_eq!(Vec::<i64>::new(), migrator.run(&mut *conn)?);
+
+        let mut rs = conn.query("SELECT COUNT(*) FROM users", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i64(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_rollback() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let migrator = rdbc::Migrator::new(vec![
+            (1, "CREATE TABLE users (id INT PRIMARY KEY)"),
+            (2, "INSERT INTO missing VALUES (1)"),
+        ]);
+        assert!(migrator.run(&mut *conn).is_err());
+        assert!(!conn.tables()?.contains(&"users".to_owned()));
+        Ok(())
+    }
+
+    #[test]
+    fn is_null() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let mut rs = conn.query("SELECT 'text', NULL", &[])?;
+        let row = rs.next_row()?.unwrap();
+        assert!(!row.is_null(0)?);
+        assert!(row.is_null(1)?);
+        assert!(row.is_null(2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn set_var() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        conn.set_var("cache_size", rdbc::Value::Int32(-4000))?;
+        let mut rs = conn.query("PRAGMA cache_size", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(-4000), rs.get_i32(0)?);
+        drop(rs);
+
+        assert!(conn
+            .set_var("writable_schema", rdbc::Value::Bool(true))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn list_tables() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        assert!(conn.tables()?.is_empty());
+        execute(&mut *conn, "CREATE TABLE b (a INT)", &vec![])?;
+        execute(&mut *conn, "CREATE TABLE a (a INT)", &vec![])?;
+        execute(&mut *conn, "CREATE VIEW c AS SELECT a FROM a", &vec![])?;
+        assert_eq!(vec!["a".to_owned(), "b".to_owned()], conn.tables()?);
+        Ok(())
+    }
+
+    #[test]
+    fn column_defaults() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (a INT DEFAULT 0, b TEXT)",
+            &vec![],
+        )?;
+
+        let meta = conn.columns("test")?;
+        assert_eq!(2, meta.num_columns());
+        assert_eq!("a", meta.column_name(0));
+        assert_eq!(DataType::Integer, meta.column_type(0));
+        assert_eq!(Some("0".to_owned()), meta.column_default(0));
+        assert_eq!("b", meta.column_name(1));
+        assert_eq!(None, meta.column_default(1));
+        // SQLite does not support comments on columns
+        assert_eq!(None, meta.column_comment(0));
+
+        // defaults are not known for the results of a query
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        let rs = stmt.execute_query(&vec![])?;
+        assert_eq!(None, rs.meta_data()?.column_default(0));
+        Ok(())
+    }
+
+    #[test]
+    fn execute_and_query_shortcuts() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        conn.execute("CREATE TABLE test (a INT, b TEXT)", &[])?;
+        let n = conn.execute(
+            "INSERT INTO test (a, b) VALUES (?, ?), (?, ?)",
+            &[
+                rdbc::Value::Int32(1),
+                rdbc::Value::String("one".to_owned()),
+                rdbc::Value::Int32(2),
+                rdbc::Value::Null,
+            ],
+        )?;
+        assert_eq!(2, n);
+
+        let mut rs = conn.query(
+            "SELECT a, b FROM test WHERE a >= ? ORDER BY a",
+            &[rdbc::Value::Int32(1)],
+        )?;
+        assert_eq!(2, rs.meta_data()?.num_columns());
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        assert_eq!(Some("one".to_owned()), rs.get_string(1)?);
+        assert!(rs.next()?);
+        assert_eq!(Some(2), rs.get_i32(0)?);
+        assert_eq!(None, rs.get_string(1)?);
+        assert!(!rs.next()?);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_utf8() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (CAST(X'FF61' AS TEXT))",
+            &vec![],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        match rs.get_string(0) {
+            Err(rdbc::Error::Conversion(msg)) => assert!(msg.contains("UTF-8"), msg),
+            other => panic!("expected conversion error, got {:?}", other),
+        }
+        assert_eq!(Some("\u{fffd}a".to_owned()), rs.get_string_lossy(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn max_rows() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (1), (2), (3), (4), (5)",
+            &vec![],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a FROM test ORDER BY a")?;
+        stmt.set_max_rows(2)?;
+        let values = stmt.query_map(&[], |row| row.get_i32(0))?;
+        assert_eq!(vec![Some(1), Some(2)], values);
+        Ok(())
+    }
+
+    #[test]
+    fn next_row() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (1, 'one'), (2, NULL)",
+            &vec![],
+        )?;
+
+        // same shape as the loop in rdbc-cli
+        let mut stmt = conn.create("SELECT a, b FROM test ORDER BY a")?;
+        let mut rs = stmt.execute_query(&[])?;
+        let meta = rs.meta_data()?;
+        let mut lines = vec![];
+        while let Some(row) = rs.next_row()? {
+            let mut line = vec![];
+            for i in 0..meta.num_columns() {
+                line.push(match meta.column_type(i) {
+                    DataType::Integer => format!("{:?}", row.get_i32(i)?),
+                    DataType::Long => format!("{:?}", row.get_i64(i)?),
+                    _ => format!("{:?}", row.get_string(i)?),
+                });
+            }
+            lines.push(line.join("\t"));
+        }
+        assert_eq!(vec!["Some(1)\tSome(\"one\")", "Some(2)\tNone"], lines);
+        Ok(())
+    }
+
+    #[test]
+    fn blob_reader() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a BLOB)", &vec![])?;
+        let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (?)",
+            &vec![rdbc::Value::Bytes(data.clone())],
+        )?;
+
+        // incremental I/O
+        let sconn = conn.as_any().downcast_ref::<SConnection>().unwrap();
+        let rowid = sconn.raw().last_insert_rowid();
+        let mut reader = sconn.blob_reader("test", "a", rowid)?;
+        let mut chunk = vec![0; 64 * 1024];
+        let mut read = vec![];
+        loop {
+            let n = reader.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            read.extend_from_slice(&chunk[..n]);
+        }
+        assert!(read == data);
+
+        // reader over the value in a result set
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        let mut read = vec![];
+        rs.get_blob_reader(0)?
+            .unwrap()
+            .read_to_end(&mut read)
+            .unwrap();
+        assert!(read == data);
+        Ok(())
+    }
+
+    #[test]
+    fn get_display() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let mut stmt = conn.prepare("SELECT date('2020-01-02'), x'DEAD', 1.5, 7, NULL")?;
+        let mut rs = stmt.execute_query(&vec![])?;
+        assert!(rs.next()?);
+        assert_eq!("2020-01-02", rs.get_display(0)?);
+        assert_eq!("\\xdead", rs.get_display(1)?);
+        assert_eq!("1.5", rs.get_display(2)?);
+        assert_eq!("7", rs.get_display(3)?);
+        assert_eq!("NULL", rs.get_display(4)?);
+        Ok(())
+    }
+
+    #[test]
+    fn cancel_query() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let token = conn.cancel_token()?;
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            token.cancel()
+        });
+
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 1000000000) \
+             SELECT count(*) FROM c",
+        )?;
+        let mut rs = stmt.execute_query(&[])?;
+        match rs.next() {
+            Err(rdbc::Error::General(msg)) => assert!(msg.contains("interrupted"), msg),
+            other => panic!("expected the query to be interrupted, got {:?}", other),
+        }
+        canceller.join().unwrap()
+    }
+
+    #[test]
+    fn prepare_typed() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, b TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, b) VALUES (1, 'one'), (2, 'two')",
+            &vec![],
+        )?;
+
+        let mut stmt = conn.prepare_typed::<(i32, String)>("SELECT a, b FROM test ORDER BY a")?;
+        assert_eq!(
+            vec![(1, "one".to_owned()), (2, "two".to_owned())],
+            stmt.query(&[])?
+        );
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Size {
+        Small,
+        Large,
+    }
+
+    impl rdbc::ToRdbcValue for Size {
+        fn to_rdbc_value(&self) -> rdbc::Value {
+            let name = match self {
+                Size::Small => "small",
+                Size::Large => "large",
+            };
+            rdbc::Value::String(name.to_owned())
+        }
+    }
+
+    impl rdbc::FromRdbcColumn for Size {
+        fn from_rdbc_value(value: rdbc::Value) -> rdbc::Result<Self> {
+            match value {
+                rdbc::Value::String(ref s) if s == "small" => Ok(Size::Small),
+                rdbc::Value::String(ref s) if s == "large" => Ok(Size::Large),
+                other => Err(rdbc::Error::Conversion(format!(
+                    "Invalid size: {:?}",
+                    other
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn bind_enum() -> rdbc::Result<()> {
+        use rdbc::ToRdbcValue;
+
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT, size TEXT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a, size) VALUES (1, ?), (2, ?), (3, NULL)",
+            &vec![Size::Large.to_rdbc_value(), Size::Small.to_rdbc_value()],
+        )?;
+
+        let mut stmt =
+            conn.prepare_typed::<(i32, Option<Size>)>("SELECT a, size FROM test ORDER BY a")?;
+        assert_eq!(
+            vec![(1, Some(Size::Large)), (2, Some(Size::Small)), (3, None)],
+            stmt.query(&[])?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn returning_keys() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (id INTEGER PRIMARY KEY, a TEXT)",
+            &vec![],
+        )?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (id, a) VALUES (10, 'x')",
+            &vec![],
+        )?;
+
+        let mut stmt = conn.prepare("INSERT INTO test (a) VALUES (?)")?;
+        let keys = stmt.execute_returning_keys(&["y".into()])?;
+        assert_eq!(vec![rdbc::Value::Int64(11)], keys);
+        drop(stmt);
+
+        let mut stmt = conn.prepare("INSERT INTO test (a) VALUES (?), (?), (?)")?;
+        let keys = stmt.execute_returning_keys(&["a".into(), "b".into(), "c".into()])?;
+        assert_eq!(
+            vec![
+                rdbc::Value::Int64(12),
+                rdbc::Value::Int64(13),
+                rdbc::Value::Int64(14)
+            ],
+            keys
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn placeholders() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let mut stmt = conn.prepare("SELECT ? + :b, ':c'")?;
+        let tokens: Vec<String> = stmt.placeholders().into_iter().map(|p| p.token).collect();
+        assert_eq!(vec!["?", ":b"], tokens);
+
+        // named parameters are bound by position
+        let rows = stmt.query_map(&[1.into(), 2.into()], |row| row.get_i64(0))?;
+        assert_eq!(vec![Some(3)], rows);
+        Ok(())
+    }
+
+    #[test]
+    fn busy_error() -> rdbc::Result<()> {
+        let path = std::env::temp_dir().join("rdbc_busy.db");
+        let _ = std::fs::remove_file(&path);
+        let url = path.to_str().unwrap();
+
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut writer = driver.connect(url)?;
+        execute(&mut *writer, "CREATE TABLE test (a INT NOT NULL)", &vec![])?;
+        execute(&mut *writer, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+
+        let mut reader = driver.connect(url)?;
+        reader
+            .as_any()
+            .downcast_ref::<SConnection>()
+            .unwrap()
+            .raw()
+            .busy_timeout(Duration::from_millis(0))
+            .map_err(to_rdbc_err)?;
+        let mut stmt = reader.prepare("SELECT a FROM test")?;
+
+        // the rows are not read until `next` is called, which fails while the lock is held
+        execute(&mut *writer, "BEGIN EXCLUSIVE", &vec![])?;
+        let mut rs = stmt.execute_query(&[])?;
+        match rs.next() {
+            Err(rdbc::Error::General(msg)) => assert!(msg.contains("locked"), msg),
+            other => panic!("expected busy error, got {:?}", other),
+        }
+        drop(rs);
+
+        execute(&mut *writer, "COMMIT", &vec![])?;
+        let mut rs = stmt.execute_query(&[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn connect_with() -> rdbc::Result<()> {
+        let path = std::env::temp_dir().join("rdbc_connect_with.db");
+        let _ = std::fs::remove_file(&path);
+
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let opts = rdbc::ConnectOptions {
+            database: Some(path.to_str().unwrap().to_owned()),
+            params: vec![("journal_mode".to_owned(), "WAL".to_owned())],
+            ..Default::default()
+        };
+        let mut conn = driver.connect_with(&opts)?;
+        execute(&mut *conn, "CREATE TABLE test (a INT NOT NULL)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (42)", &vec![])?;
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        assert_eq!(vec![Some(42)], stmt.query_map(&[], |row| row.get_i32(0))?);
+        drop(stmt);
+
+        let mut stmt = conn.prepare("PRAGMA journal_mode")?;
+        let mut rs = stmt.execute_query(&[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some("wal".to_owned()), rs.get_string(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn position() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        let mut stmt = conn.prepare("SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3")?;
+        let mut rs = stmt.execute_query(&[])?;
+        assert_eq!(0, rs.position());
+        for i in 1..=3 {
+            assert!(rs.next()?);
+            assert_eq!(i, rs.position());
+        }
+        assert!(!rs.next()?);
+        assert_eq!(3, rs.position());
+        assert_eq!(None, rs.row_count());
+        Ok(())
+    }
+
+    #[test]
+    fn insert_many_reserved_words() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (id INT, \"order\" INT)",
+            &vec![],
+        )?;
+        let rows = vec![vec![1.into(), 10.into()], vec![2.into(), 20.into()]];
+        assert_eq!(2, conn.insert_many("test", &["id", "order"], &rows)?);
+
+        let mut stmt = conn.prepare("SELECT \"order\" FROM test ORDER BY id")?;
+        assert_eq!(
+            vec![Some(10), Some(20)],
+            stmt.query_map(&[], |row| row.get_i32(0))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn upsert() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(
+            &mut *conn,
+            "CREATE TABLE test (id INT PRIMARY KEY, \"value\" TEXT)",
+            &vec![],
+        )?;
+        for value in &["one", "two"] {
+            conn.upsert(
+                "test",
+                &["id", "value"],
+                &[1.into(), (*value).into()],
+                &["id"],
+                &["value"],
+            )?;
+        }
+
+        let mut stmt = conn.prepare("SELECT id, \"value\" FROM test")?;
+        let rows = stmt.query_map(&[], |row| Ok((row.get_i32(0)?, row.get_string(1)?)))?;
+        assert_eq!(vec![(Some(1), Some("two".to_owned()))], rows);
+        Ok(())
+    }
+
+    #[test]
+    fn bigint() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a BIGINT)", &vec![])?;
+        execute(
+            &mut *conn,
+            "INSERT INTO test (a) VALUES (?)",
+            &vec![rdbc::Value::Int64(1 << 40)],
+        )?;
+
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+        let mut rs = stmt.execute_query(&[])?;
+        assert_eq!(DataType::Long, rs.meta_data()?.column_type(0));
+        assert!(rs.next()?);
+        assert_eq!(Some(1 << 40), rs.get_i64(0)?);
+        assert_eq!(vec![rdbc::Value::Int64(1 << 40)], rs.get_row_values()?);
+        Ok(())
+    }
+
+    #[test]
+    fn integer_affinity() -> rdbc::Result<()> {
+        assert_eq!(DataType::Integer, to_rdbc_type(Some("int")));
+        assert_eq!(DataType::Integer, to_rdbc_type(Some("INT(11)")));
+        for t in &["INTEGER", "bigint", "INT8", "UNSIGNED BIG INT", "smallint"] {
+            assert_eq!(DataType::Long, to_rdbc_type(Some(t)), "{}", t);
+        }
+        assert_eq!(DataType::Utf8, to_rdbc_type(Some("TEXT")));
+        assert_eq!(DataType::Utf8, to_rdbc_type(None));
+
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INTEGER, b int8)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a, b) VALUES (1, 2)", &vec![])?;
+        let mut rs = conn.query("SELECT a, b FROM test", &[])?;
+        assert!(rs.next()?);
+        assert_eq!(
+            vec![rdbc::Value::Int64(1), rdbc::Value::Int64(2)],
+            rs.get_row_values()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn query_page() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT NOT NULL)", &vec![])?;
+        let rows: Vec<Vec<rdbc::Value>> = (0..10).map(|i| vec![i.into()]).collect();
+        conn.insert_many("test", &["a"], &rows)?;
+
+        let mut pages = vec![];
+        for offset in (0..10).step_by(3) {
+            let mut rs = conn.query_page("SELECT a FROM test ORDER BY a", &[], 3, offset)?;
+            let mut page = vec![];
+            while let Some(row) = rs.next_row()? {
+                page.push(row.get_i32(0)?.unwrap());
+            }
+            pages.push(page);
+        }
+        assert_eq!(
+            vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9]],
+            pages
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finalize_on_drop() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT UNIQUE)", &vec![])?;
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (1)", &vec![])?;
+        {
+            // leave the statement with a pending constraint error before it is finalized
+            let mut stmt = conn.prepare("INSERT INTO test (a) VALUES (1)")?;
+            assert!(stmt.execute_update(&[]).is_err());
+        }
+        // the connection is still usable after the statement has been dropped
+        execute(&mut *conn, "INSERT INTO test (a) VALUES (2)", &vec![])?;
+        Ok(())
+    }
+
+    #[test]
+    fn bind_array() -> rdbc::Result<()> {
+        let driver: Arc<dyn rdbc::Driver> = Arc::new(SqliteDriver::new());
+        let mut conn = driver.connect("")?;
+        execute(&mut *conn, "CREATE TABLE test (a INT NOT NULL)", &vec![])?;
+        for i in 1..=5 {
+            execute(
+                &mut *conn,
+                "INSERT INTO test (a) VALUES (?)",
+                &vec![i.into()],
+            )?;
+        }
+
+        let mut stmt = conn.prepare("SELECT a FROM test WHERE a IN rarray(?) ORDER BY a")?;
+        let params = vec![rdbc::Value::Array(vec![2.into(), 4.into(), 6.into()])];
+        let rows = stmt.query_map(&params, |row| row.get_i32(0))?;
+        assert_eq!(vec![Some(2), Some(4)], rows);
+        Ok(())
+    }
+
     fn execute(
         conn: &mut dyn Connection,
         sql: &str,
@@ -0,0 +1,344 @@
+//! A connection that caches the results of queries
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    CancelToken, Connection, DbError, Dialect, MaterializedResultSet, Notification, Placeholder,
+    Result, ResultSet, ResultSetMetaData, Statement, Value,
+};
+
+/// A connection that caches the results of queries, keyed by the SQL and parameters, so that
+/// running the same query again returns the cached rows without executing it. This is only
+/// suitable for queries whose results can be reused, such as reference data that rarely
+/// changes.
+///
+/// Results are read into memory when they are cached. Entries expire after `ttl`, and once the
+/// cache holds `capacity` results the least recently used one is evicted. Any update executed
+/// through the connection clears the cache, but changes made by other connections are not
+/// detected until the entries expire.
+///
+/// `Connection::query` returns cached results without contacting the database. Statements
+/// created with `prepare` are still prepared by the underlying connection, but
+/// `execute_query` returns cached results.
+pub struct CachingConnection {
+    conn: Box<dyn Connection>,
+    cache: ResultCache,
+}
+
+impl CachingConnection {
+    pub fn new(conn: Box<dyn Connection>, capacity: usize, ttl: Duration) -> Self {
+        CachingConnection {
+            conn,
+            cache: ResultCache {
+                entries: HashMap::new(),
+                capacity,
+                ttl,
+                clock: 0,
+            },
+        }
+    }
+
+    /// Remove all cached results
+    pub fn clear(&mut self) {
+        self.cache.entries.clear();
+    }
+}
+
+/// The SQL and the debug representation of the parameters and row limit
+type CacheKey = (String, String);
+
+struct ResultCache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    capacity: usize,
+    ttl: Duration,
+    /// Incremented on every access, to find the least recently used entry
+    clock: u64,
+}
+
+struct CacheEntry {
+    rs: MaterializedResultSet,
+    created: Instant,
+    last_used: u64,
+}
+
+impl ResultCache {
+    fn key(sql: &str, params: &[Value], max_rows: Option<u64>) -> CacheKey {
+        (sql.to_owned(), format!("{:?} {:?}", params, max_rows))
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<MaterializedResultSet> {
+        self.clock += 1;
+        let expired = match self.entries.get_mut(key) {
+            Some(entry) if entry.created.elapsed() < self.ttl => {
+                entry.last_used = self.clock;
+                return Some(entry.rs.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(key);
+        }
+        None
+    }
+
+    fn insert(&mut self, key: CacheKey, rs: MaterializedResultSet) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                rs,
+                created: Instant::now(),
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Return the cached result of a query, or run the query and cache its result
+    fn get_or_query<F>(&mut self, key: CacheKey, query: F) -> Result<MaterializedResultSet>
+    where
+        F: FnOnce() -> Result<MaterializedResultSet>,
+    {
+        if let Some(rs) = self.get(&key) {
+            return Ok(rs);
+        }
+        let rs = query()?;
+        self.insert(key, rs.clone());
+        Ok(rs)
+    }
+}
+
+impl Connection for CachingConnection {
+    fn create(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let stmt = self.conn.create(sql)?;
+        Ok(Box::new(CachingStatement {
+            stmt,
+            sql: sql.to_owned(),
+            cache: &mut self.cache,
+            max_rows: None,
+        }))
+    }
+
+    fn prepare(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let stmt = self.conn.prepare(sql)?;
+        Ok(Box::new(CachingStatement {
+            stmt,
+            sql: sql.to_owned(),
+            cache: &mut self.cache,
+            max_rows: None,
+        }))
+    }
+
+    fn prepare_cached(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let stmt = self.conn.prepare_cached(sql)?;
+        Ok(Box::new(CachingStatement {
+            stmt,
+            sql: sql.to_owned(),
+            cache: &mut self.cache,
+            max_rows: None,
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.conn.as_any()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self.conn.as_any_mut()
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        let conn = &mut self.conn;
+        let rs = self
+            .cache
+            .get_or_query(ResultCache::key(sql, params, None), || {
+                let mut rs = conn.query(sql, params)?;
+                MaterializedResultSet::from_result_set(&mut *rs)
+            })?;
+        Ok(Box::new(rs))
+    }
+
+    fn dialect(&self) -> &dyn Dialect {
+        self.conn.dialect()
+    }
+
+    fn is_valid(&mut self) -> bool {
+        self.conn.is_valid()
+    }
+
+    fn set_placeholder(&mut self, ch: char) -> Result<()> {
+        self.conn.set_placeholder(ch)
+    }
+
+    fn set_read_only(&mut self, on: bool) -> Result<()> {
+        self.conn.set_read_only(on)
+    }
+
+    fn set_var(&mut self, name: &str, value: Value) -> Result<()> {
+        self.conn.set_var(name, value)
+    }
+
+    fn listen(&mut self, channel: &str) -> Result<()> {
+        self.conn.listen(channel)
+    }
+
+    fn notifications(&mut self) -> Result<Box<dyn Iterator<Item = Notification>>> {
+        self.conn.notifications()
+    }
+
+    fn tables(&mut self) -> Result<Vec<String>> {
+        self.conn.tables()
+    }
+
+    fn columns(&mut self, table: &str) -> Result<Box<dyn ResultSetMetaData>> {
+        self.conn.columns(table)
+    }
+
+    fn cancel_token(&self) -> Result<CancelToken> {
+        self.conn.cancel_token()
+    }
+
+    fn last_error(&self) -> Option<DbError> {
+        self.conn.last_error()
+    }
+}
+
+struct CachingStatement<'a> {
+    stmt: Box<dyn Statement + 'a>,
+    sql: String,
+    cache: &'a mut ResultCache,
+    max_rows: Option<u64>,
+}
+
+impl<'a> Statement for CachingStatement<'a> {
+    fn execute_query(&mut self, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        let stmt = &mut self.stmt;
+        let rs =
+            self.cache
+                .get_or_query(ResultCache::key(&self.sql, params, self.max_rows), || {
+                    let mut rs = stmt.execute_query(params)?;
+                    MaterializedResultSet::from_result_set(&mut *rs)
+                })?;
+        Ok(Box::new(rs))
+    }
+
+    fn execute_update(&mut self, params: &[Value]) -> Result<u64> {
+        self.cache.entries.clear();
+        self.stmt.execute_update(params)
+    }
+
+    fn execute_many(&mut self, param_sets: &[Vec<Value>]) -> Result<u64> {
+        self.cache.entries.clear();
+        self.stmt.execute_many(param_sets)
+    }
+
+    fn execute_returning_keys(&mut self, params: &[Value]) -> Result<Vec<Value>> {
+        self.cache.entries.clear();
+        self.stmt.execute_returning_keys(params)
+    }
+
+    fn rewritten_sql(&self) -> &str {
+        self.stmt.rewritten_sql()
+    }
+
+    fn returns_rows(&self) -> bool {
+        self.stmt.returns_rows()
+    }
+
+    fn placeholders(&self) -> Vec<Placeholder> {
+        self.stmt.placeholders()
+    }
+
+    fn set_max_rows(&mut self, max_rows: u64) -> Result<()> {
+        self.stmt.set_max_rows(max_rows)?;
+        self.max_rows = Some(max_rows);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.stmt.reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDriver;
+    use crate::Driver;
+
+    fn caching_connection(
+        capacity: usize,
+        ttl: Duration,
+    ) -> Result<(CachingConnection, MockDriver)> {
+        let driver = MockDriver::default();
+        let conn = driver.connect("mock://")?;
+        Ok((CachingConnection::new(conn, capacity, ttl), driver))
+    }
+
+    #[test]
+    fn cache_hit() -> Result<()> {
+        let (mut conn, driver) = caching_connection(10, Duration::from_secs(60))?;
+        for _ in 0..2 {
+            let mut rs = conn.query("SELECT a FROM test WHERE a = ?", &[Value::Int32(1)])?;
+            assert!(rs.next()?);
+            assert_eq!(Some(1), rs.get_i32(0)?);
+        }
+        assert_eq!(1, driver.queries());
+
+        // different parameters are cached separately
+        conn.query("SELECT a FROM test WHERE a = ?", &[Value::Int32(2)])?;
+        assert_eq!(2, driver.queries());
+
+        // prepared statements share the cache
+        let mut stmt = conn.prepare("SELECT a FROM test WHERE a = ?")?;
+        stmt.execute_query(&[Value::Int32(2)])?;
+        assert_eq!(2, driver.queries());
+        Ok(())
+    }
+
+    #[test]
+    fn update_clears_cache() -> Result<()> {
+        let (mut conn, driver) = caching_connection(10, Duration::from_secs(60))?;
+        conn.query("SELECT a FROM test", &[])?;
+        conn.execute("DELETE FROM test", &[])?;
+        conn.query("SELECT a FROM test", &[])?;
+        assert_eq!(2, driver.queries());
+        Ok(())
+    }
+
+    #[test]
+    fn expiry_and_eviction() -> Result<()> {
+        let (mut conn, driver) = caching_connection(10, Duration::from_secs(0))?;
+        conn.query("SELECT a FROM test", &[])?;
+        conn.query("SELECT a FROM test", &[])?;
+        assert_eq!(2, driver.queries());
+
+        let (mut conn, driver) = caching_connection(2, Duration::from_secs(60))?;
+        conn.query("SELECT 1", &[])?;
+        conn.query("SELECT 2", &[])?;
+        conn.query("SELECT 1", &[])?;
+        // evicts SELECT 2, which is the least recently used
+        conn.query("SELECT 3", &[])?;
+        assert_eq!(3, driver.queries());
+        conn.query("SELECT 1", &[])?;
+        assert_eq!(3, driver.queries());
+        conn.query("SELECT 2", &[])?;
+        assert_eq!(4, driver.queries());
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+//! SQL dialects, describing how values and other SQL fragments are rendered for a particular
+//! database
+
+use crate::{Error, Result, Value};
+
+/// Describes how SQL literals are written for a particular database
+pub trait Dialect {
+    /// Render a string as a quoted SQL string literal
+    fn string_literal(&self, s: &str) -> String {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+
+    /// Render bytes as a SQL binary literal
+    fn bytes_literal(&self, b: &[u8]) -> String {
+        format!("X'{}'", to_hex(b))
+    }
+
+    /// Render a boolean as a SQL literal
+    fn bool_literal(&self, b: bool) -> String {
+        if b { "1" } else { "0" }.to_owned()
+    }
+
+    /// Quote an identifier, such as a table or column name, so that reserved words and mixed
+    /// case names can be used. Note that quoted identifiers are case-sensitive.
+    fn quote_identifier(&self, name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    /// The maximum number of bound parameters allowed in a single statement
+    fn max_params(&self) -> usize {
+        999
+    }
+
+    /// Render a clause limiting a query to `limit` rows, skipping the first `offset` rows
+    fn limit_clause(&self, limit: u64, offset: u64) -> String {
+        format!("LIMIT {} OFFSET {}", limit, offset)
+    }
+
+    /// Append a limit clause to a query so that it returns a single page of results. Queries
+    /// that already contain `LIMIT`, including in a subquery, are rejected rather than risk
+    /// producing invalid SQL.
+    fn paginate(&self, sql: &str, limit: u64, offset: u64) -> Result<String> {
+        let sql = sql.trim_end().trim_end_matches(';').trim_end();
+        if contains_keyword(sql, "LIMIT") {
+            return Err(Error::General(format!(
+                "Cannot paginate a query that already has a LIMIT: {}",
+                sql
+            )));
+        }
+        Ok(format!("{} {}", sql, self.limit_clause(limit, offset)))
+    }
+
+    /// Render the clause appended to an `INSERT` statement so that a row whose
+    /// `conflict_columns` match an existing row updates `update_columns` of that row instead,
+    /// using the values from the `INSERT`. The row is left unchanged if there are no
+    /// `update_columns`.
+    fn upsert_clause(&self, conflict_columns: &[&str], update_columns: &[&str]) -> String {
+        let conflict = conflict_columns
+            .iter()
+            .map(|name| self.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if update_columns.is_empty() {
+            return format!("ON CONFLICT ({}) DO NOTHING", conflict);
+        }
+        let updates = update_columns
+            .iter()
+            .map(|name| {
+                let name = self.quote_identifier(name);
+                format!("{} = excluded.{}", name, name)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("ON CONFLICT ({}) DO UPDATE SET {}", conflict, updates)
+    }
+}
+
+/// Determine whether the SQL contains a keyword outside of quoted strings and identifiers
+fn contains_keyword(sql: &str, keyword: &str) -> bool {
+    words(sql)
+        .iter()
+        .any(|(word, _)| word.eq_ignore_ascii_case(keyword))
+}
+
+/// Determine whether executing the SQL produces a result set, based on its leading keyword,
+/// without sending it to the database. Queries such as `SELECT`, `VALUES` and `SHOW` return
+/// rows, as do `INSERT`, `UPDATE` and `DELETE` statements with a `RETURNING` clause. For a
+/// statement starting with `WITH`, the statement following the common table expressions is
+/// used. Anything else, including DDL and statements that are not recognized, is assumed not
+/// to return rows.
+pub fn returns_rows(sql: &str) -> bool {
+    let words = words(sql);
+    let mut top_level = words
+        .iter()
+        .filter(|(_, depth)| *depth == 0)
+        .map(|(word, _)| word.to_uppercase());
+    // a query may start with a parenthesis, as in `(SELECT 1) UNION (SELECT 2)`
+    let statement = match words.first() {
+        Some((word, _)) if word.eq_ignore_ascii_case("WITH") => top_level.find(|word| {
+            ["SELECT", "VALUES", "INSERT", "UPDATE", "DELETE"].contains(&word.as_str())
+        }),
+        Some((word, _)) => Some(word.to_uppercase()),
+        None => None,
+    };
+    match statement.as_ref().map(String::as_str) {
+        Some("SELECT") | Some("VALUES") | Some("TABLE") | Some("SHOW") | Some("EXPLAIN")
+        | Some("DESCRIBE") | Some("DESC") | Some("PRAGMA") => true,
+        Some("INSERT") | Some("UPDATE") | Some("DELETE") => words
+            .iter()
+            .any(|(word, depth)| *depth == 0 && word.eq_ignore_ascii_case("RETURNING")),
+        _ => false,
+    }
+}
+
+/// Split SQL into words, skipping quoted strings and identifiers, comments and punctuation.
+/// Each word is returned with its depth of nesting in parentheses.
+fn words(sql: &str) -> Vec<(String, usize)> {
+    let mut words = vec![];
+    let mut depth = 0usize;
+    let mut word = String::new();
+    let mut chars = sql.chars().chain(std::iter::once(' ')).peekable();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            words.push((std::mem::replace(&mut word, String::new()), depth));
+        }
+        match c {
+            '\'' | '"' | '`' => {
+                // a doubled quote character is an escaped quote, which is skipped as two strings
+                while let Some(next) = chars.next() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                while let Some(next) = chars.next() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                while let Some(next) = chars.next() {
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    words
+}
+
+/// A parameter placeholder in SQL, as reported by `Statement::placeholders`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    /// The placeholder as written, such as `?` or `:name`
+    pub token: String,
+    /// The one-based position of the placeholder. Parameters are bound by position, so this
+    /// placeholder is bound to `params[ordinal - 1]`, even if it is named.
+    pub ordinal: usize,
+    /// The byte offset of the placeholder in the SQL
+    pub offset: usize,
+}
+
+/// Find the parameter placeholders in SQL, which are either the `placeholder` character or a
+/// name prefixed with a colon, skipping quoted strings and identifiers, comments and
+/// Postgres `::` casts
+pub fn find_placeholders(sql: &str, placeholder: char) -> Vec<Placeholder> {
+    let mut found = vec![];
+    let mut add = |token: &str, offset: usize| {
+        found.push(Placeholder {
+            token: token.to_owned(),
+            ordinal: found.len() + 1,
+            offset,
+        })
+    };
+    let mut chars = sql.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek().map(|(_, c)| *c) == Some('-') => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().map(|(_, c)| *c) == Some('*') => {
+                chars.next();
+                let mut prev = ' ';
+                while let Some((_, next)) = chars.next() {
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+            }
+            c if c == placeholder => add(&sql[offset..offset + c.len_utf8()], offset),
+            ':' => match chars.peek() {
+                Some((_, ':')) => {
+                    chars.next();
+                }
+                Some((_, next)) if next.is_alphabetic() || *next == '_' => {
+                    let mut end = offset + 1;
+                    while let Some((i, next)) = chars.peek() {
+                        if !next.is_alphanumeric() && *next != '_' {
+                            break;
+                        }
+                        end = i + next.len_utf8();
+                        chars.next();
+                    }
+                    add(&sql[offset..end], offset);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Split a script into its statements at semicolons outside of quoted strings, identifiers and
+/// comments, dropping empty statements
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut statement = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ';' {
+            statements.push(std::mem::replace(&mut statement, String::new()));
+            continue;
+        }
+        statement.push(c);
+        match c {
+            '\'' | '"' | '`' => {
+                while let Some(next) = chars.next() {
+                    statement.push(next);
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                while let Some(next) = chars.next() {
+                    statement.push(next);
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                statement.push(chars.next().unwrap());
+                let mut prev = ' ';
+                while let Some(next) = chars.next() {
+                    statement.push(next);
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+            }
+            _ => {}
+        }
+    }
+    statements.push(statement);
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !words(s).is_empty())
+        .collect()
+}
+
+/// Dialect using ANSI SQL quoting rules
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GenericDialect;
+
+impl Dialect for GenericDialect {}
+
+/// Postgres dialect
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PostgresDialect;
+
+impl Dialect for PostgresDialect {
+    /// Strings containing backslashes are written as escape string constants so that they
+    /// are interpreted the same way regardless of `standard_conforming_strings`
+    fn string_literal(&self, s: &str) -> String {
+        if s.contains('\\') {
+            format!("E'{}'", s.replace('\\', "\\\\").replace('\'', "''"))
+        } else {
+            format!("'{}'", s.replace('\'', "''"))
+        }
+    }
+
+    fn bytes_literal(&self, b: &[u8]) -> String {
+        format!("decode('{}', 'hex')", to_hex(b))
+    }
+
+    fn bool_literal(&self, b: bool) -> String {
+        if b { "TRUE" } else { "FALSE" }.to_owned()
+    }
+
+    fn max_params(&self) -> usize {
+        65535
+    }
+}
+
+/// MySQL dialect
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MySQLDialect;
+
+impl Dialect for MySQLDialect {
+    fn quote_identifier(&self, name: &str) -> String {
+        format!("`{}`", name.replace('`', "``"))
+    }
+
+    /// MySQL treats backslash as an escape character within string literals
+    fn string_literal(&self, s: &str) -> String {
+        let mut quoted = String::with_capacity(s.len() + 2);
+        quoted.push('\'');
+        for c in s.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '\'' => quoted.push_str("\\'"),
+                '\0' => quoted.push_str("\\0"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\x1a' => quoted.push_str("\\Z"),
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        quoted
+    }
+
+    fn max_params(&self) -> usize {
+        65535
+    }
+
+    /// MySQL updates the existing row when the insert conflicts with any primary key or unique
+    /// index, so `conflict_columns` are only used to produce a no-op update when there are no
+    /// `update_columns`
+    fn upsert_clause(&self, conflict_columns: &[&str], update_columns: &[&str]) -> String {
+        let columns = if update_columns.is_empty() {
+            &conflict_columns[..1]
+        } else {
+            update_columns
+        };
+        let updates = columns
+            .iter()
+            .map(|name| {
+                let name = self.quote_identifier(name);
+                format!("{} = VALUES({})", name, name)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("ON DUPLICATE KEY UPDATE {}", updates)
+    }
+}
+
+/// SQLite dialect
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SqliteDialect;
+
+impl Dialect for SqliteDialect {}
+
+fn to_hex(b: &[u8]) -> String {
+    b.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+impl Value {
+    /// Render this value as a SQL literal that can safely be embedded in a statement for the
+    /// given dialect
+    pub fn to_sql_literal(&self, dialect: &dyn Dialect) -> String {
+        match self {
+            Value::Bool(b) => dialect.bool_literal(*b),
+            Value::String(s) => dialect.string_literal(s),
+            Value::Char(c) => dialect.string_literal(&c.to_string()),
+            Value::Bytes(b) => dialect.bytes_literal(b),
+            Value::Interval(i) => dialect.string_literal(&i.to_string()),
+            Value::Record(fields) => format!(
+                "ROW({})",
+                fields
+                    .iter()
+                    .map(|v| v.to_sql_literal(dialect))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Array(values) => format!(
+                "ARRAY[{}]",
+                values
+                    .iter()
+                    .map(|v| v.to_sql_literal(dialect))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Range { .. } => dialect.string_literal(&self.to_display_string()),
+            #[cfg(feature = "bigdecimal")]
+            Value::Decimal(d) => d.to_string(),
+            Value::Null
+            | Value::Int8(_)
+            | Value::Int16(_)
+            | Value::Int32(_)
+            | Value::Int64(_)
+            | Value::UInt32(_)
+            | Value::Float32(_)
+            | Value::Float64(_) => self.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_literals() {
+        let s = Value::String("it's a \\ test".to_owned());
+        assert_eq!("'it''s a \\ test'", s.to_sql_literal(&GenericDialect));
+        assert_eq!("'it''s a \\ test'", s.to_sql_literal(&SqliteDialect));
+        assert_eq!("E'it''s a \\\\ test'", s.to_sql_literal(&PostgresDialect));
+        assert_eq!("'it\\'s a \\\\ test'", s.to_sql_literal(&MySQLDialect));
+
+        let s = Value::String("plain".to_owned());
+        assert_eq!("'plain'", s.to_sql_literal(&PostgresDialect));
+    }
+
+    #[test]
+    fn bytes_literals() {
+        let b = Value::Bytes(vec![0xDE, 0xAD, 0x01]);
+        assert_eq!("X'DEAD01'", b.to_sql_literal(&MySQLDialect));
+        assert_eq!("X'DEAD01'", b.to_sql_literal(&SqliteDialect));
+        assert_eq!(
+            "decode('DEAD01', 'hex')",
+            b.to_sql_literal(&PostgresDialect)
+        );
+    }
+
+    #[test]
+    fn other_literals() {
+        assert_eq!("TRUE", Value::Bool(true).to_sql_literal(&PostgresDialect));
+        assert_eq!("1", Value::Bool(true).to_sql_literal(&MySQLDialect));
+        assert_eq!("0", Value::Bool(false).to_sql_literal(&SqliteDialect));
+        assert_eq!("NULL", Value::Null.to_sql_literal(&PostgresDialect));
+        assert_eq!("-12", Value::Int32(-12).to_sql_literal(&MySQLDialect));
+        assert_eq!(
+            "'NaN'",
+            Value::Float64(std::f64::NAN).to_sql_literal(&PostgresDialect)
+        );
+    }
+
+    #[test]
+    fn quote_identifiers() {
+        assert_eq!("\"order\"", GenericDialect.quote_identifier("order"));
+        assert_eq!("\"a\"\"b\"", PostgresDialect.quote_identifier("a\"b"));
+        assert_eq!("`order`", MySQLDialect.quote_identifier("order"));
+        assert_eq!("`a``b`", MySQLDialect.quote_identifier("a`b"));
+    }
+
+    #[test]
+    fn upsert_clauses() {
+        assert_eq!(
+            "ON CONFLICT (\"id\") DO UPDATE SET \"a\" = excluded.\"a\", \"b\" = excluded.\"b\"",
+            PostgresDialect.upsert_clause(&["id"], &["a", "b"])
+        );
+        assert_eq!(
+            "ON CONFLICT (\"x\", \"y\") DO NOTHING",
+            SqliteDialect.upsert_clause(&["x", "y"], &[])
+        );
+        assert_eq!(
+            "ON DUPLICATE KEY UPDATE `a` = VALUES(`a`)",
+            MySQLDialect.upsert_clause(&["id"], &["a"])
+        );
+        assert_eq!(
+            "ON DUPLICATE KEY UPDATE `id` = VALUES(`id`)",
+            MySQLDialect.upsert_clause(&["id"], &[])
+        );
+    }
+
+    #[test]
+    fn statements_returning_rows() {
+        for sql in &[
+            "SELECT 1",
+            "  select a FROM t",
+            "(SELECT 1) UNION (SELECT 2)",
+            "VALUES (1), (2)",
+            "SHOW TABLES",
+            "EXPLAIN SELECT 1",
+            "PRAGMA table_info(t)",
+            "-- comment\nSELECT 1",
+            "/* INSERT */ SELECT 1",
+            "WITH x AS (INSERT INTO t VALUES (1) RETURNING a) SELECT * FROM x",
+            "WITH RECURSIVE x(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM x) SELECT n FROM x",
+            "INSERT INTO t (a) VALUES (1) RETURNING id",
+            "UPDATE t SET a = 'returning' RETURNING a",
+            "WITH x AS (SELECT 1) DELETE FROM t WHERE a IN (SELECT * FROM x) RETURNING a",
+        ] {
+            assert!(returns_rows(sql), "{}", sql);
+        }
+
+        for sql in &[
+            "",
+            "INSERT INTO t (a) VALUES (1)",
+            "INSERT INTO t (a) SELECT a FROM u",
+            "UPDATE t SET a = 'RETURNING'",
+            "UPDATE t SET \"returning\" = 1",
+            "DELETE FROM t",
+            "WITH x AS (SELECT 1) DELETE FROM t WHERE a IN (SELECT * FROM x)",
+            "WITH x AS (DELETE FROM t RETURNING a) INSERT INTO u SELECT a FROM x",
+            "CREATE TABLE t (a INT)",
+            "DROP TABLE t",
+            "BEGIN",
+            "-- SELECT\nDELETE FROM t",
+        ] {
+            assert!(!returns_rows(sql), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn paginate() -> Result<()> {
+        assert_eq!(
+            "SELECT a FROM t ORDER BY a LIMIT 3 OFFSET 6",
+            GenericDialect.paginate("SELECT a FROM t ORDER BY a;\n", 3, 6)?
+        );
+        assert_eq!(
+            "SELECT 'no limit' AS \"limit\" LIMIT 1 OFFSET 0",
+            PostgresDialect.paginate("SELECT 'no limit' AS \"limit\"", 1, 0)?
+        );
+        assert!(MySQLDialect
+            .paginate("SELECT a FROM t limit 5", 3, 0)
+            .is_err());
+        assert!(SqliteDialect
+            .paginate("SELECT * FROM (SELECT a FROM t LIMIT 5)", 3, 0)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn placeholders() {
+        let sql = "SELECT a::text, ':x' FROM t -- ?\n WHERE b = ? AND c = :name /* ? */ AND d = ?";
+        let found = find_placeholders(sql, '?');
+        assert_eq!(
+            vec![("?", 1, 44), (":name", 2, 54), ("?", 3, 76)],
+            found
+                .iter()
+                .map(|p| (p.token.as_str(), p.ordinal, p.offset))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("?", &sql[44..45]);
+        assert_eq!(":name", &sql[54..59]);
+        assert_eq!("?", &sql[76..77]);
+        assert_eq!(1, find_placeholders("SELECT $ WHERE a = ?", '$').len());
+    }
+
+    #[test]
+    fn split_script() {
+        assert_eq!(
+            vec![
+                "CREATE TABLE t (a TEXT DEFAULT ';')",
+                "-- a comment; with a semicolon\nINSERT INTO t VALUES ('x')",
+                "/* ; */ SELECT \"a;b\" FROM t",
+            ],
+            split_statements(
+                "CREATE TABLE t (a TEXT DEFAULT ';');\n\
+                 -- a comment; with a semicolon\nINSERT INTO t VALUES ('x');;\n\
+                 /* ; */ SELECT \"a;b\" FROM t;\n-- trailing comment"
+            )
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! Client-side extraction of values from JSON documents, used by `ResultSet::get_json_path`
+
+use crate::{Error, Result, Value};
+
+/// A parsed JSON value. Arrays and objects keep their source text so that they can be returned
+/// without re-serializing them.
+enum Json<'a> {
+    Null,
+    Bool(bool),
+    Number(&'a str),
+    String(String),
+    Array(&'a str, Vec<Json<'a>>),
+    Object(&'a str, Vec<(String, Json<'a>)>),
+}
+
+/// A step in a JSON path
+#[derive(Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Extract the value at `path` from a JSON document, returning `None` if the path does not
+/// exist or the value is a JSON `null`.
+///
+/// Paths use the subset of SQL/JSON path syntax supported by both Postgres and MySQL: `$`
+/// followed by any number of `.key`, `."quoted key"` and `[index]` steps. Integers are returned
+/// as `Int32` or `Int64` depending on their size, other numbers as `Float64`, and arrays and
+/// objects as their JSON text.
+pub(crate) fn extract_path(json: &str, path: &str) -> Result<Option<Value>> {
+    let steps = parse_path(path)?;
+    let mut parser = Parser {
+        json,
+        pos: 0,
+        depth: 0,
+    };
+    let doc = parser.parse_document()?;
+
+    let mut current = &doc;
+    for step in &steps {
+        let next = match (step, current) {
+            (Step::Key(key), Json::Object(_, fields)) => {
+                fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            (Step::Index(i), Json::Array(_, items)) => items.get(*i),
+            _ => None,
+        };
+        match next {
+            Some(v) => current = v,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(match current {
+        Json::Null => None,
+        Json::Bool(b) => Some(Value::Bool(*b)),
+        Json::Number(n) => Some(to_number(n)?),
+        Json::String(s) => Some(Value::String(s.clone())),
+        Json::Array(raw, _) | Json::Object(raw, _) => Some(Value::String((*raw).to_owned())),
+    })
+}
+
+fn to_number(n: &str) -> Result<Value> {
+    if !n.contains(|c| c == '.' || c == 'e' || c == 'E') {
+        if let Ok(n) = n.parse::<i32>() {
+            return Ok(Value::Int32(n));
+        }
+        if let Ok(n) = n.parse::<i64>() {
+            return Ok(Value::Int64(n));
+        }
+    }
+    n.parse::<f64>()
+        .map(Value::Float64)
+        .map_err(|_| Error::Conversion(format!("Invalid JSON number: {}", n)))
+}
+
+fn parse_path(path: &str) -> Result<Vec<Step>> {
+    let invalid = || Error::General(format!("Invalid JSON path: {}", path));
+    let path = path.trim();
+    if !path.starts_with('$') {
+        return Err(invalid());
+    }
+    let chars: Vec<char> = path[1..].chars().collect();
+    let mut steps = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' if chars.get(i + 1) == Some(&'"') => {
+                let end = (i + 2..chars.len())
+                    .find(|j| chars[*j] == '"')
+                    .ok_or_else(invalid)?;
+                steps.push(Step::Key(chars[i + 2..end].iter().collect()));
+                i = end + 1;
+            }
+            '.' => {
+                let end = (i + 1..chars.len())
+                    .find(|j| chars[*j] == '.' || chars[*j] == '[')
+                    .unwrap_or_else(|| chars.len());
+                if end == i + 1 {
+                    return Err(invalid());
+                }
+                steps.push(Step::Key(chars[i + 1..end].iter().collect()));
+                i = end;
+            }
+            '[' => {
+                let end = (i + 1..chars.len())
+                    .find(|j| chars[*j] == ']')
+                    .ok_or_else(invalid)?;
+                let index: String = chars[i + 1..end].iter().collect();
+                steps.push(Step::Index(index.trim().parse().map_err(|_| invalid())?));
+                i = end + 1;
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(steps)
+}
+
+/// The maximum nesting of arrays and objects, so that a malicious document cannot overflow the
+/// stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+    /// The number of arrays and objects enclosing the current position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_document(&mut self) -> Result<Json<'a>> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.json.len() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json<'a>> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.parse_string().map(Json::String),
+            Some(b'[') => {
+                self.enter()?;
+                let mut items = vec![];
+                if !self.consume(b']') {
+                    loop {
+                        items.push(self.parse_value()?);
+                        if self.consume(b']') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Array(&self.json[start..self.pos], items))
+            }
+            Some(b'{') => {
+                self.enter()?;
+                let mut fields = vec![];
+                if !self.consume(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.parse_string()?;
+                        self.expect(b':')?;
+                        fields.push((key, self.parse_value()?));
+                        if self.consume(b'}') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Object(&self.json[start..self.pos], fields))
+            }
+            Some(b'-') | Some(b'0'..=b'9') => {
+                while let Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E')
+                | Some(b'0'..=b'9') = self.peek()
+                {
+                    self.pos += 1;
+                }
+                Ok(Json::Number(&self.json[start..self.pos]))
+            }
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut s = String::new();
+        loop {
+            let rest = &self.json[self.pos..];
+            let c = rest
+                .chars()
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("invalid escape"))?;
+                    self.pos += 1;
+                    match escape {
+                        b'"' => s.push('"'),
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{8}'),
+                        b'f' => s.push('\u{c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => s.push(self.parse_unicode_escape()?),
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parse the hex digits of a `\u` escape, combining UTF-16 surrogate pairs
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let high = self.parse_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.json[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        std::char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .json
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).map_err(|_| self.error("invalid unicode escape"))
+    }
+
+    /// Consume the opening character of an array or object, failing if it is nested too deeply
+    fn enter(&mut self) -> Result<()> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, text: &str, value: Json<'a>) -> Result<Json<'a>> {
+        if self.json[self.pos..].starts_with(text) {
+            self.pos += text.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace and consume `c` if it is the next character
+    fn consume(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c as char)))
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Conversion(format!("Invalid JSON at offset {}: {}", self.pos, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_scalars() -> Result<()> {
+        let json = r#"{"k": 42, "big": 5000000000, "f": 1.5e1, "s": "a\"é😀",
+            "b": true, "n": null, "nested": {"a": [1, {"b key": "x"}]}}"#;
+        assert_eq!(Some(Value::Int32(42)), extract_path(json, "$.k")?);
+        assert_eq!(
+            Some(Value::Int64(5_000_000_000)),
+            extract_path(json, "$.big")?
+        );
+        assert_eq!(Some(Value::Float64(15.0)), extract_path(json, "$.f")?);
+        assert_eq!(
+            Some(Value::String("a\"\u{e9}\u{1f600}".to_owned())),
+            extract_path(json, "$.s")?
+        );
+        assert_eq!(Some(Value::Bool(true)), extract_path(json, "$.b")?);
+        assert_eq!(None, extract_path(json, "$.n")?);
+        assert_eq!(None, extract_path(json, "$.missing")?);
+        assert_eq!(None, extract_path(json, "$.k.a")?);
+        assert_eq!(
+            Some(Value::String("x".to_owned())),
+            extract_path(json, r#"$.nested.a[1]."b key""#)?
+        );
+        assert_eq!(
+            Some(Value::String(r#"[1, {"b key": "x"}]"#.to_owned())),
+            extract_path(json, "$.nested.a")?
+        );
+        assert_eq!(Some(Value::Int32(7)), extract_path(" 7 ", "$")?);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(extract_path(r#"{"k": 1"#, "$.k").is_err());
+        assert!(extract_path(r#"{"k": 1} x"#, "$.k").is_err());
+        assert!(extract_path(r#"{"k": 1}"#, "k").is_err());
+        assert!(extract_path(r#"{"k": 1}"#, "$.").is_err());
+        assert!(extract_path("[1]", "$[x]").is_err());
+    }
+
+    #[test]
+    fn nesting_limit() -> Result<()> {
+        let nested = |depth| format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(
+            Some(Value::Int32(1)),
+            extract_path(&nested(MAX_DEPTH), &format!("${}", "[0]".repeat(MAX_DEPTH)))?
+        );
+        assert!(extract_path(&nested(MAX_DEPTH + 1), "$").is_err());
+        assert!(extract_path(&"{\"a\":".repeat(100_000), "$").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Result set wrapper that enforces a maximum number of rows
+
+use std::io::Read;
+
+use crate::{Column, Interval, Result, ResultSet, ResultSetMetaData, Value};
+
+/// A result set that yields at most `max_rows` rows from another result set. This is used
+/// by drivers to implement `Statement::set_max_rows`.
+pub struct LimitedResultSet<'a> {
+    rs: Box<dyn ResultSet + 'a>,
+    max_rows: u64,
+    rows: u64,
+}
+
+impl<'a> LimitedResultSet<'a> {
+    pub fn new(rs: Box<dyn ResultSet + 'a>, max_rows: u64) -> Self {
+        LimitedResultSet {
+            rs,
+            max_rows,
+            rows: 0,
+        }
+    }
+
+    /// Wrap a result set if a limit has been set, otherwise return it unchanged
+    pub fn wrap(rs: Box<dyn ResultSet + 'a>, max_rows: Option<u64>) -> Box<dyn ResultSet + 'a> {
+        match max_rows {
+            Some(max_rows) => Box::new(Self::new(rs, max_rows)),
+            None => rs,
+        }
+    }
+}
+
+macro_rules! impl_resultset_fns {
+    ($($fn: ident -> $ty: ty),*) => {
+        $(
+            fn $fn(&self, i: u64) -> Result<Option<$ty>> {
+                self.rs.$fn(i)
+            }
+        )*
+    }
+}
+
+impl<'a> ResultSet for LimitedResultSet<'a> {
+    fn meta_data(&self) -> Result<Box<dyn ResultSetMetaData>> {
+        self.rs.meta_data()
+    }
+
+    fn columns(&self) -> Result<Vec<Column>> {
+        self.rs.columns()
+    }
+
+    fn next(&mut self) -> Result<bool> {
+        if self.rows < self.max_rows && self.rs.next()? {
+            self.rows += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.rows
+    }
+
+    fn row_count(&self) -> Option<u64> {
+        self.rs.row_count().map(|n| std::cmp::min(n, self.max_rows))
+    }
+
+    /// Limiting the rows that are returned does not change the rows that were affected
+    fn rows_affected(&self) -> Option<u64> {
+        self.rs.rows_affected()
+    }
+
+    fn seek(&mut self, row: u64) -> Result<bool> {
+        if row < self.max_rows {
+            let found = self.rs.seek(row)?;
+            self.rows = self.rs.position();
+            Ok(found)
+        } else {
+            // leave the cursor on the last row within the limit
+            if self.max_rows > 0 {
+                self.rs.seek(self.max_rows - 1)?;
+            }
+            self.rows = self.rs.position();
+            Ok(false)
+        }
+    }
+
+    fn previous(&mut self) -> Result<bool> {
+        let found = self.rs.previous()?;
+        self.rows = self.rs.position();
+        Ok(found)
+    }
+
+    impl_resultset_fns! {
+        get_i8 -> i8,
+        get_i16 -> i16,
+        get_i32 -> i32,
+        get_i64 -> i64,
+        get_f32 -> f32,
+        get_f64 -> f64,
+        get_string -> String,
+        get_string_lossy -> String,
+        get_bytes -> Vec<u8>,
+        get_byte -> u8,
+        get_u32 -> u32,
+        get_char -> char,
+        get_interval -> Interval,
+        get_record -> Vec<Value>,
+        get_range -> Value
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    fn get_big_decimal(&self, i: u64) -> Result<Option<bigdecimal::BigDecimal>> {
+        self.rs.get_big_decimal(i)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn get_datetime_tz(&self, i: u64) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        self.rs.get_datetime_tz(i)
+    }
+
+    fn is_null(&self, i: u64) -> Result<bool> {
+        self.rs.is_null(i)
+    }
+
+    fn get_blob_reader(&self, i: u64) -> Result<Option<Box<dyn Read + '_>>> {
+        self.rs.get_blob_reader(i)
+    }
+
+    fn get_json_path(&self, i: u64, path: &str) -> Result<Option<Value>> {
+        self.rs.get_json_path(i, path)
+    }
+
+    fn get_row_values(&self) -> Result<Vec<Value>> {
+        self.rs.get_row_values()
+    }
+
+    fn get_display(&self, i: u64) -> Result<String> {
+        self.rs.get_display(i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Column, DataType, MaterializedResultSet};
+
+    #[test]
+    fn limit_rows() -> Result<()> {
+        let rows = (0..5).map(|i| vec![Value::Int32(i)]).collect();
+        let rs = MaterializedResultSet::new(vec![Column::new("a", DataType::Integer)], rows);
+        let mut rs = LimitedResultSet::new(Box::new(rs), 2);
+        assert!(rs.next()?);
+        assert_eq!(Some(0), rs.get_i32(0)?);
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        assert!(!rs.next()?);
+        assert!(!rs.next()?);
+
+        // seeking cannot reach rows beyond the limit
+        assert!(!rs.seek(3)?);
+        assert_eq!(2, rs.position());
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        assert!(rs.previous()?);
+        assert_eq!(Some(0), rs.get_i32(0)?);
+        assert!(rs.next()?);
+        assert!(!rs.next()?);
+        Ok(())
+    }
+}
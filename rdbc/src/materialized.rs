@@ -0,0 +1,479 @@
+//! A result set that holds all of its rows in memory
+
+use std::convert::TryFrom;
+
+use crate::{
+    single_char, Column, Error, Interval, Numeric, Result, ResultSet, ResultSetMetaData, Value,
+};
+
+/// A result set over rows that have already been read from the database. This is used when a
+/// result set must outlive the statement that produced it, and since it owns all of its data it
+/// can also be sent to another thread.
+#[derive(Clone)]
+pub struct MaterializedResultSet {
+    meta: Vec<Column>,
+    rows: Vec<Vec<Value>>,
+    /// Number of times `next` has been called, so the current row is `rows[i - 1]`
+    i: usize,
+    rows_affected: Option<u64>,
+}
+
+impl MaterializedResultSet {
+    pub fn new(meta: Vec<Column>, rows: Vec<Vec<Value>>) -> Self {
+        MaterializedResultSet {
+            meta,
+            rows,
+            i: 0,
+            rows_affected: None,
+        }
+    }
+
+    /// Set the number of rows affected by the statement that produced the rows
+    pub fn with_rows_affected(mut self, rows_affected: Option<u64>) -> Self {
+        self.rows_affected = rows_affected;
+        self
+    }
+
+    /// Read all remaining rows from another result set
+    pub fn from_result_set(rs: &mut dyn ResultSet) -> Result<Self> {
+        let meta = rs.meta_data()?;
+        let meta = (0..meta.num_columns())
+            .map(|i| {
+                Column::new(&meta.column_name(i), meta.column_type(i))
+                    .with_type_name(meta.column_type_name(i))
+            })
+            .collect();
+        let mut rows = vec![];
+        while rs.next()? {
+            rows.push(rs.get_row_values()?);
+        }
+        Ok(Self::new(meta, rows).with_rows_affected(rs.rows_affected()))
+    }
+
+    /// Create a result set containing only the named columns, in the order given. The new
+    /// result set is positioned before the first row.
+    pub fn project(&self, names: &[&str]) -> Result<Self> {
+        let indices = names
+            .iter()
+            .map(|name| {
+                self.meta
+                    .iter()
+                    .position(|c| c.name == *name)
+                    .ok_or_else(|| Error::General(format!("Unknown column: {}", name)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let meta = indices.iter().map(|i| self.meta[*i].clone()).collect();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| indices.iter().map(|i| row[*i].clone()).collect())
+            .collect();
+        Ok(Self::new(meta, rows).with_rows_affected(self.rows_affected))
+    }
+
+    fn value(&self, i: u64) -> Result<&Value> {
+        self.i
+            .checked_sub(1)
+            .and_then(|row| self.rows.get(row))
+            .ok_or_else(|| Error::General("No current row".to_owned()))?
+            .get(i as usize)
+            .ok_or_else(|| Error::General(format!("Column index {} out of range", i)))
+    }
+}
+
+fn type_mismatch<T>(value: &Value, type_name: &str) -> Result<T> {
+    Err(Error::General(format!(
+        "Cannot convert {:?} to {}",
+        value, type_name
+    )))
+}
+
+macro_rules! impl_int_fns {
+    ($($fn: ident -> $ty: ident),*) => {
+        $(
+            fn $fn(&self, i: u64) -> Result<Option<$ty>> {
+                match self.value(i)? {
+                    Value::Null => Ok(None),
+                    v => match v.as_numeric() {
+                        Some(Numeric::Int(n)) => $ty::try_from(n).map(Some).map_err(|_| {
+                            Error::General(format!("{} is out of range for {}", n, stringify!($ty)))
+                        }),
+                        _ => type_mismatch(v, stringify!($ty)),
+                    },
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_float_fns {
+    ($($fn: ident -> $ty: ident),*) => {
+        $(
+            fn $fn(&self, i: u64) -> Result<Option<$ty>> {
+                match self.value(i)? {
+                    Value::Null => Ok(None),
+                    v => match v.as_numeric() {
+                        Some(Numeric::Int(n)) => Ok(Some(n as $ty)),
+                        Some(Numeric::Float(n)) => Ok(Some(n as $ty)),
+                        None => type_mismatch(v, stringify!($ty)),
+                    },
+                }
+            }
+        )*
+    }
+}
+
+impl ResultSet for MaterializedResultSet {
+    fn meta_data(&self) -> Result<Box<dyn ResultSetMetaData>> {
+        Ok(Box::new(self.meta.clone()))
+    }
+
+    fn columns(&self) -> Result<Vec<Column>> {
+        Ok(self.meta.clone())
+    }
+
+    fn next(&mut self) -> Result<bool> {
+        if self.i < self.rows.len() {
+            self.i += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.i as u64
+    }
+
+    fn row_count(&self) -> Option<u64> {
+        Some(self.rows.len() as u64)
+    }
+
+    fn rows_affected(&self) -> Option<u64> {
+        self.rows_affected
+    }
+
+    fn is_null(&self, i: u64) -> Result<bool> {
+        Ok(*self.value(i)? == Value::Null)
+    }
+
+    fn seek(&mut self, row: u64) -> Result<bool> {
+        if row < self.rows.len() as u64 {
+            self.i = row as usize + 1;
+            Ok(true)
+        } else {
+            self.i = self.rows.len();
+            Ok(false)
+        }
+    }
+
+    fn previous(&mut self) -> Result<bool> {
+        self.i = self.i.saturating_sub(1);
+        Ok(self.i > 0)
+    }
+
+    impl_int_fns! {
+        get_i8 -> i8,
+        get_i16 -> i16,
+        get_i32 -> i32,
+        get_i64 -> i64,
+        get_byte -> u8,
+        get_u32 -> u32
+    }
+
+    impl_float_fns! {
+        get_f32 -> f32,
+        get_f64 -> f64
+    }
+
+    fn get_string(&self, i: u64) -> Result<Option<String>> {
+        Ok(match self.value(i)? {
+            Value::Null => None,
+            Value::String(s) => Some(s.clone()),
+            v => Some(v.to_display_string()),
+        })
+    }
+
+    fn get_bytes(&self, i: u64) -> Result<Option<Vec<u8>>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            Value::Bytes(b) => Ok(Some(b.clone())),
+            Value::String(s) => Ok(Some(s.as_bytes().to_vec())),
+            v => type_mismatch(v, "bytes"),
+        }
+    }
+
+    fn get_char(&self, i: u64) -> Result<Option<char>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            Value::Char(c) => Ok(Some(*c)),
+            Value::String(s) => single_char(s).map(Some),
+            v => type_mismatch(v, "char"),
+        }
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    fn get_big_decimal(&self, i: u64) -> Result<Option<bigdecimal::BigDecimal>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            Value::Decimal(d) => Ok(Some(d.clone())),
+            v => match v.to_display_string().parse() {
+                Ok(d) => Ok(Some(d)),
+                Err(_) => type_mismatch(v, "decimal"),
+            },
+        }
+    }
+
+    fn get_interval(&self, i: u64) -> Result<Option<Interval>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            Value::Interval(interval) => Ok(Some(*interval)),
+            v => type_mismatch(v, "interval"),
+        }
+    }
+
+    fn get_record(&self, i: u64) -> Result<Option<Vec<Value>>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            Value::Record(fields) => Ok(Some(fields.clone())),
+            v => type_mismatch(v, "record"),
+        }
+    }
+
+    fn get_range(&self, i: u64) -> Result<Option<Value>> {
+        match self.value(i)? {
+            Value::Null => Ok(None),
+            v @ Value::Range { .. } => Ok(Some(v.clone())),
+            v => type_mismatch(v, "range"),
+        }
+    }
+
+    fn get_row_values(&self) -> Result<Vec<Value>> {
+        (0..self.meta.len() as u64)
+            .map(|i| self.value(i).map(|v| v.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+
+    #[test]
+    fn read_rows() -> Result<()> {
+        let mut rs = MaterializedResultSet::new(
+            vec![
+                Column::new("a", DataType::Integer),
+                Column::new("b", DataType::Utf8),
+            ],
+            vec![
+                vec![Value::Int32(1), Value::String("one".to_owned())],
+                vec![Value::Int64(300), Value::Null],
+            ],
+        );
+        assert!(rs.get_i32(0).is_err());
+        assert_eq!(0, rs.position());
+        assert_eq!(Some(2), rs.row_count());
+
+        assert!(rs.next()?);
+        assert_eq!(1, rs.position());
+        assert_eq!(Some(1), rs.get_i8(0)?);
+        assert_eq!(Some(1), rs.get_byte(0)?);
+        assert_eq!(Some(1.0), rs.get_f64(0)?);
+        assert_eq!(Some("1".to_owned()), rs.get_string(0)?);
+        assert_eq!(Some("one".to_owned()), rs.get_string(1)?);
+        assert!(rs.get_i32(1).is_err());
+
+        assert!(rs.next()?);
+        assert!(rs.get_i8(0).is_err());
+        assert!(rs.get_byte(0).is_err());
+        assert_eq!(Some(300), rs.get_u32(0)?);
+        assert_eq!(Some(300), rs.get_i16(0)?);
+        assert_eq!(None, rs.get_string(1)?);
+        assert!(!rs.is_null(0)?);
+        assert!(rs.is_null(1)?);
+        assert_eq!(vec![Value::Int64(300), Value::Null], rs.get_row_values()?);
+
+        assert!(!rs.next()?);
+        assert_eq!(2, rs.position());
+        Ok(())
+    }
+
+    #[test]
+    fn seek_and_previous() -> Result<()> {
+        let rows = (0..5).map(|n| vec![Value::Int32(n)]).collect();
+        let mut rs = MaterializedResultSet::new(vec![Column::new("a", DataType::Integer)], rows);
+        assert!(rs.seek(3)?);
+        assert_eq!(4, rs.position());
+        assert_eq!(Some(3), rs.get_i32(0)?);
+        assert!(rs.previous()?);
+        assert_eq!(Some(2), rs.get_i32(0)?);
+        assert!(rs.next()?);
+        assert_eq!(Some(3), rs.get_i32(0)?);
+
+        assert!(!rs.seek(10)?);
+        assert_eq!(Some(4), rs.get_i32(0)?);
+        assert!(!rs.next()?);
+
+        assert!(rs.seek(0)?);
+        assert!(!rs.previous()?);
+        assert_eq!(0, rs.position());
+        assert!(!rs.previous()?);
+        assert!(rs.next()?);
+        assert_eq!(Some(0), rs.get_i32(0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn project() -> Result<()> {
+        let rs = MaterializedResultSet::new(
+            vec![
+                Column::new("id", DataType::Integer),
+                Column::new("name", DataType::Utf8),
+                Column::new("age", DataType::Integer),
+            ],
+            vec![
+                vec![
+                    Value::Int32(1),
+                    Value::String("one".to_owned()),
+                    Value::Int32(30),
+                ],
+                vec![Value::Int32(2), Value::Null, Value::Null],
+            ],
+        );
+        let mut projected = rs.project(&["name", "id"])?;
+        let meta = projected.meta_data()?;
+        assert_eq!(2, meta.num_columns());
+        assert_eq!("name", meta.column_name(0));
+        assert_eq!(DataType::Utf8, meta.column_type(0));
+        assert_eq!("id", meta.column_name(1));
+
+        assert!(projected.next()?);
+        assert_eq!(
+            vec![Value::String("one".to_owned()), Value::Int32(1)],
+            projected.get_row_values()?
+        );
+        assert!(projected.next()?);
+        assert_eq!(
+            vec![Value::Null, Value::Int32(2)],
+            projected.get_row_values()?
+        );
+        assert!(!projected.next()?);
+
+        assert!(rs.project(&["id", "missing"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn copy_rows_affected() -> Result<()> {
+        let meta = vec![Column::new("a", DataType::Integer)];
+        let mut rs = MaterializedResultSet::new(meta, vec![vec![Value::Int32(1)]])
+            .with_rows_affected(Some(1));
+        let copy = MaterializedResultSet::from_result_set(&mut rs)?;
+        assert_eq!(Some(1), copy.rows_affected());
+        assert_eq!(
+            None,
+            MaterializedResultSet::new(vec![], vec![]).rows_affected()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<MaterializedResultSet>();
+    }
+
+    #[test]
+    fn json_path() -> Result<()> {
+        let mut rs = MaterializedResultSet::new(
+            vec![Column::new("doc", DataType::Utf8)],
+            vec![vec![Value::String(r#"{"k": 42}"#.to_owned())]],
+        );
+        assert!(rs.next()?);
+        assert_eq!(Some(Value::Int32(42)), rs.get_json_path(0, "$.k")?);
+        Ok(())
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn big_decimal() -> Result<()> {
+        let digits = "1234567890123456789012345678901234.567890";
+        let d: bigdecimal::BigDecimal = digits.parse().unwrap();
+        let mut rs = MaterializedResultSet::new(
+            vec![
+                Column::new("a", DataType::Decimal),
+                Column::new("b", DataType::Utf8),
+            ],
+            vec![vec![
+                Value::from(d.clone()),
+                Value::String(digits.to_owned()),
+            ]],
+        );
+        assert!(rs.next()?);
+        assert_eq!(Some(d.clone()), rs.get_big_decimal(0)?);
+        assert_eq!(Some(d.clone()), rs.get_big_decimal(1)?);
+        assert_eq!(Some(digits.to_owned()), rs.get_string(0)?);
+        assert_eq!(Value::Decimal(d), rs.get_row_values()?[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn column_aliases() {
+        let meta = vec![
+            Column::new("total", DataType::Long),
+            Column::new("b", DataType::Integer).with_source_name(Some("a".to_owned())),
+        ];
+        assert_eq!("b", meta[1].alias());
+        assert_eq!(Some("a"), meta[1].source_name());
+        assert_eq!(None, meta.column_source_name(0));
+        assert_eq!(Some("a".to_owned()), meta.column_source_name(1));
+    }
+
+    #[test]
+    fn columns() -> Result<()> {
+        let meta = vec![
+            Column::new("a", DataType::Integer).with_type_name(Some("INT".to_owned())),
+            Column::new("b", DataType::Utf8),
+        ];
+        let rs = MaterializedResultSet::new(meta.clone(), vec![]);
+        assert_eq!(meta, rs.columns()?);
+        Ok(())
+    }
+
+    #[test]
+    fn get_value_by_name() -> Result<()> {
+        let meta = vec![
+            Column::new("id", DataType::Integer),
+            Column::new("name", DataType::Utf8),
+        ];
+        let rows = vec![vec![Value::Int32(1), Value::Null]];
+        let mut rs: Box<dyn ResultSet> = Box::new(MaterializedResultSet::new(meta, rows));
+        let row = rs.next_row()?.unwrap();
+        assert_eq!(Value::Int32(1), row.get_value_by_name("id")?);
+        assert_eq!(Value::Null, row.get_value_by_name("name")?);
+        assert!(row.get_value_by_name("ID").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn row_to_map() -> Result<()> {
+        let meta = vec![
+            Column::new("id", DataType::Integer),
+            Column::new("name", DataType::Utf8),
+            Column::new("id", DataType::Integer),
+        ];
+        let rows = vec![vec![
+            Value::Int32(1),
+            Value::String("one".to_owned()),
+            Value::Int32(2),
+        ]];
+        let mut rs: Box<dyn ResultSet> = Box::new(MaterializedResultSet::new(meta, rows));
+        let map = rs.next_row()?.unwrap().to_map()?;
+        assert_eq!(2, map.len());
+        assert_eq!(Some(&Value::Int32(1)), map.get("id"));
+        assert_eq!(Some(&Value::String("one".to_owned())), map.get("name"));
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Simple schema migrations, applied in version order and recorded in a tracking table
+
+use crate::dialect::split_statements;
+use crate::{Connection, Error, Result};
+
+/// Name of the table used to record which migrations have been applied
+const MIGRATIONS_TABLE: &str = "_rdbc_migrations";
+
+/// Applies an ordered list of SQL migrations to a database, recording the version of each
+/// applied migration in the `_rdbc_migrations` table so that running the migrator again only
+/// applies migrations that are new.
+///
+/// ```rust,ignore
+/// let migrator = Migrator::new(vec![
+///     (1, "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(100))"),
+///     (2, "ALTER TABLE users ADD COLUMN email VARCHAR(100)"),
+/// ]);
+/// migrator.run(&mut *conn)?;
+/// ```
+pub struct Migrator {
+    migrations: Vec<(i64, String)>,
+}
+
+impl Migrator {
+    /// Create a migrator from `(version, sql)` pairs, in ascending order of version. The SQL for
+    /// each migration may contain several statements separated by semicolons.
+    pub fn new<S: Into<String>>(migrations: Vec<(i64, S)>) -> Self {
+        Migrator {
+            migrations: migrations
+                .into_iter()
+                .map(|(version, sql)| (version, sql.into()))
+                .collect(),
+        }
+    }
+
+    /// Apply any migrations that have not already been applied, returning their versions. The
+    /// pending migrations are applied in a single transaction, which is rolled back if any of
+    /// them fails. Note that MySQL implicitly commits DDL statements, so a failed migration may
+    /// be partially applied there.
+    pub fn run(&self, conn: &mut dyn Connection) -> Result<Vec<i64>> {
+        if let Some(w) = self.migrations.windows(2).find(|w| w[0].0 >= w[1].0) {
+            return Err(Error::General(format!(
+                "Migration versions must be in ascending order but {} is followed by {}",
+                w[0].0, w[1].0
+            )));
+        }
+
+        execute(
+            conn,
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (version BIGINT NOT NULL PRIMARY KEY)",
+                MIGRATIONS_TABLE
+            ),
+        )?;
+        let applied = applied_versions(conn)?;
+        let pending: Vec<&(i64, String)> = self
+            .migrations
+            .iter()
+            .filter(|(version, _)| !applied.contains(version))
+            .collect();
+        if pending.is_empty() {
+            return Ok(vec![]);
+        }
+
+        execute(conn, "BEGIN")?;
+        if let Err(e) = apply(conn, &pending) {
+            // report the original error rather than any failure to roll back
+            let _ = execute(conn, "ROLLBACK");
+            return Err(e);
+        }
+        execute(conn, "COMMIT")?;
+        Ok(pending.iter().map(|(version, _)| *version).collect())
+    }
+}
+
+fn applied_versions(conn: &mut dyn Connection) -> Result<Vec<i64>> {
+    let mut rs = conn.query(&format!("SELECT version FROM {}", MIGRATIONS_TABLE), &[])?;
+    let mut versions = vec![];
+    while rs.next()? {
+        if let Some(version) = rs.get_i64(0)? {
+            versions.push(version);
+        }
+    }
+    Ok(versions)
+}
+
+fn apply(conn: &mut dyn Connection, migrations: &[&(i64, String)]) -> Result<()> {
+    for (version, sql) in migrations {
+        for statement in split_statements(sql) {
+            execute(conn, &statement)?;
+        }
+        execute(
+            conn,
+            &format!(
+                "INSERT INTO {} (version) VALUES ({})",
+                MIGRATIONS_TABLE, version
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+/// Execute a statement without preparing it, since some databases cannot prepare transaction
+/// control statements
+fn execute(conn: &mut dyn Connection, sql: &str) -> Result<u64> {
+    conn.create(sql)?.execute_update(&[])
+}
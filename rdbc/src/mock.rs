@@ -0,0 +1,178 @@
+//! A fake driver for testing the connection wrappers, whose connections count the statements
+//! they prepare and execute and can be disconnected by the test
+
+use std::any::Any;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    Column, Connection, DataType, Driver, Error, MaterializedResultSet, Result, ResultSet,
+    Statement, Value,
+};
+
+/// Driver that hands out fake connections. Statements fail to prepare if their SQL starts with
+/// `INVALID` or has been added with `break_statement`.
+#[derive(Default)]
+pub(crate) struct MockDriver {
+    state: Arc<MockState>,
+}
+
+/// State shared by a driver and its connections
+#[derive(Default)]
+struct MockState {
+    /// Whether each connection that has been opened is still connected
+    connections: Mutex<Vec<Arc<AtomicBool>>>,
+    broken: Mutex<Vec<String>>,
+    prepares: AtomicUsize,
+    cache_hits: AtomicUsize,
+    queries: AtomicUsize,
+    updates: AtomicUsize,
+}
+
+impl MockDriver {
+    /// Disconnect every connection that has been opened, so that they are no longer valid
+    pub(crate) fn disconnect(&self) {
+        for connected in self.state.connections.lock().unwrap().iter() {
+            connected.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Make statements with the given SQL fail to prepare
+    pub(crate) fn break_statement(&self, sql: &str) {
+        self.state.broken.lock().unwrap().push(sql.to_owned());
+    }
+
+    /// The number of connections that have been opened
+    pub(crate) fn connections(&self) -> usize {
+        self.state.connections.lock().unwrap().len()
+    }
+
+    /// The number of statements that have been prepared, including those that were found in a
+    /// connection's statement cache
+    pub(crate) fn prepares(&self) -> usize {
+        self.state.prepares.load(Ordering::SeqCst)
+    }
+
+    /// The number of statements prepared with `prepare_cached` that were already in the
+    /// connection's statement cache
+    pub(crate) fn cache_hits(&self) -> usize {
+        self.state.cache_hits.load(Ordering::SeqCst)
+    }
+
+    /// The number of queries that have been executed
+    pub(crate) fn queries(&self) -> usize {
+        self.state.queries.load(Ordering::SeqCst)
+    }
+
+    /// The number of updates that have been executed
+    pub(crate) fn updates(&self) -> usize {
+        self.state.updates.load(Ordering::SeqCst)
+    }
+}
+
+impl Driver for MockDriver {
+    fn connect_timeout(&self, _url: &str, _timeout: Duration) -> Result<Box<dyn Connection>> {
+        let connected = Arc::new(AtomicBool::new(true));
+        self.state
+            .connections
+            .lock()
+            .unwrap()
+            .push(connected.clone());
+        Ok(Box::new(MockConnection {
+            connected,
+            cache: HashSet::new(),
+            state: self.state.clone(),
+        }))
+    }
+}
+
+struct MockConnection {
+    connected: Arc<AtomicBool>,
+    /// The SQL of the statements prepared with `prepare_cached`
+    cache: HashSet<String>,
+    state: Arc<MockState>,
+}
+
+/// Statement that returns a single row holding its first parameter, or 1 if it has none
+struct MockStatement {
+    connected: Arc<AtomicBool>,
+    state: Arc<MockState>,
+    sql: String,
+}
+
+fn check_connected(connected: &AtomicBool) -> Result<()> {
+    if connected.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        Err(Error::General("connection closed".to_owned()))
+    }
+}
+
+impl MockConnection {
+    fn check(&self, sql: &str) -> Result<()> {
+        check_connected(&self.connected)?;
+        if sql.starts_with("INVALID") || self.state.broken.lock().unwrap().iter().any(|s| s == sql)
+        {
+            return Err(Error::General("syntax error".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+impl Connection for MockConnection {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn create(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.prepare(sql)
+    }
+
+    fn prepare(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.check(sql)?;
+        self.state.prepares.fetch_add(1, Ordering::SeqCst);
+        Ok(Box::new(MockStatement {
+            connected: self.connected.clone(),
+            state: self.state.clone(),
+            sql: sql.replace('?', "$1"),
+        }))
+    }
+
+    fn prepare_cached(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.check(sql)?;
+        if !self.cache.insert(sql.to_owned()) {
+            self.state.cache_hits.fetch_add(1, Ordering::SeqCst);
+        }
+        self.prepare(sql)
+    }
+
+    fn is_valid(&mut self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+}
+
+impl Statement for MockStatement {
+    fn execute_query(&mut self, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        check_connected(&self.connected)?;
+        self.state.queries.fetch_add(1, Ordering::SeqCst);
+        let rows = vec![vec![params.get(0).cloned().unwrap_or(Value::Int32(1))]];
+        let meta = vec![Column::new("a", DataType::Integer)];
+        Ok(Box::new(MaterializedResultSet::new(meta, rows)))
+    }
+
+    fn execute_update(&mut self, _params: &[Value]) -> Result<u64> {
+        check_connected(&self.connected)?;
+        self.state.updates.fetch_add(1, Ordering::SeqCst);
+        Ok(1)
+    }
+
+    fn rewritten_sql(&self) -> &str {
+        &self.sql
+    }
+}
@@ -0,0 +1,295 @@
+//! Hooks for collecting metrics about the statements that are executed
+
+use std::any::Any;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    CancelToken, ConnectOptions, Connection, DbError, Dialect, Driver, Notification, Placeholder,
+    Result, ResultSet, ResultSetMetaData, Statement, Value,
+};
+
+/// Receives the timing of each statement executed through an `ObservedConnection`, for example
+/// to record metrics or tracing spans
+pub trait QueryObserver: Send + Sync {
+    /// Called after a statement has been executed. `rows` is the number of rows affected by an
+    /// update, or the number of rows in a result set if the driver knows it in advance. For
+    /// queries, `duration` is the time taken to execute the query and does not include the
+    /// time spent reading the result set.
+    fn on_execute(&self, sql: &str, duration: Duration, rows: Option<u64>);
+}
+
+/// A driver whose connections report every statement they execute to an observer
+pub struct ObservedDriver {
+    driver: Arc<dyn Driver>,
+    observer: Arc<dyn QueryObserver>,
+}
+
+impl ObservedDriver {
+    pub fn new(driver: Arc<dyn Driver>, observer: Arc<dyn QueryObserver>) -> Self {
+        ObservedDriver { driver, observer }
+    }
+}
+
+impl Driver for ObservedDriver {
+    fn connect_timeout(&self, url: &str, timeout: Duration) -> Result<Box<dyn Connection>> {
+        let conn = self.driver.connect_timeout(url, timeout)?;
+        Ok(Box::new(ObservedConnection::new(
+            conn,
+            self.observer.clone(),
+        )))
+    }
+
+    fn connect_with(&self, opts: &ConnectOptions) -> Result<Box<dyn Connection>> {
+        let conn = self.driver.connect_with(opts)?;
+        Ok(Box::new(ObservedConnection::new(
+            conn,
+            self.observer.clone(),
+        )))
+    }
+}
+
+/// A connection that reports every statement it executes to an observer. `as_any` returns the
+/// wrapped connection so that it can still be downcast to the driver's connection type.
+pub struct ObservedConnection {
+    conn: Box<dyn Connection>,
+    observer: Arc<dyn QueryObserver>,
+}
+
+impl ObservedConnection {
+    pub fn new(conn: Box<dyn Connection>, observer: Arc<dyn QueryObserver>) -> Self {
+        ObservedConnection { conn, observer }
+    }
+}
+
+impl Connection for ObservedConnection {
+    fn create(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let observer = self.observer.clone();
+        let stmt = self.conn.create(sql)?;
+        Ok(Box::new(ObservedStatement {
+            stmt,
+            sql: sql.to_owned(),
+            observer,
+        }))
+    }
+
+    fn prepare(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let observer = self.observer.clone();
+        let stmt = self.conn.prepare(sql)?;
+        Ok(Box::new(ObservedStatement {
+            stmt,
+            sql: sql.to_owned(),
+            observer,
+        }))
+    }
+
+    fn prepare_cached(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        let observer = self.observer.clone();
+        let stmt = self.conn.prepare_cached(sql)?;
+        Ok(Box::new(ObservedStatement {
+            stmt,
+            sql: sql.to_owned(),
+            observer,
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.conn.as_any()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self.conn.as_any_mut()
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        let start = Instant::now();
+        let rs = self.conn.query(sql, params)?;
+        self.observer
+            .on_execute(sql, start.elapsed(), rs.row_count());
+        Ok(rs)
+    }
+
+    fn dialect(&self) -> &dyn Dialect {
+        self.conn.dialect()
+    }
+
+    fn is_valid(&mut self) -> bool {
+        self.conn.is_valid()
+    }
+
+    fn set_placeholder(&mut self, ch: char) -> Result<()> {
+        self.conn.set_placeholder(ch)
+    }
+
+    fn set_read_only(&mut self, on: bool) -> Result<()> {
+        self.conn.set_read_only(on)
+    }
+
+    fn set_var(&mut self, name: &str, value: Value) -> Result<()> {
+        self.conn.set_var(name, value)
+    }
+
+    fn listen(&mut self, channel: &str) -> Result<()> {
+        self.conn.listen(channel)
+    }
+
+    fn notifications(&mut self) -> Result<Box<dyn Iterator<Item = Notification>>> {
+        self.conn.notifications()
+    }
+
+    fn tables(&mut self) -> Result<Vec<String>> {
+        self.conn.tables()
+    }
+
+    fn columns(&mut self, table: &str) -> Result<Box<dyn ResultSetMetaData>> {
+        self.conn.columns(table)
+    }
+
+    fn cancel_token(&self) -> Result<CancelToken> {
+        self.conn.cancel_token()
+    }
+
+    fn last_error(&self) -> Option<DbError> {
+        self.conn.last_error()
+    }
+}
+
+struct ObservedStatement<'a> {
+    stmt: Box<dyn Statement + 'a>,
+    sql: String,
+    observer: Arc<dyn QueryObserver>,
+}
+
+impl<'a> Statement for ObservedStatement<'a> {
+    fn execute_query(&mut self, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        let start = Instant::now();
+        let rs = self.stmt.execute_query(params)?;
+        self.observer
+            .on_execute(&self.sql, start.elapsed(), rs.row_count());
+        Ok(rs)
+    }
+
+    fn execute_update(&mut self, params: &[Value]) -> Result<u64> {
+        let start = Instant::now();
+        let rows = self.stmt.execute_update(params)?;
+        self.observer
+            .on_execute(&self.sql, start.elapsed(), Some(rows));
+        Ok(rows)
+    }
+
+    fn execute_many(&mut self, param_sets: &[Vec<Value>]) -> Result<u64> {
+        let start = Instant::now();
+        let rows = self.stmt.execute_many(param_sets)?;
+        self.observer
+            .on_execute(&self.sql, start.elapsed(), Some(rows));
+        Ok(rows)
+    }
+
+    fn execute_returning_keys(&mut self, params: &[Value]) -> Result<Vec<Value>> {
+        let start = Instant::now();
+        let keys = self.stmt.execute_returning_keys(params)?;
+        self.observer.on_execute(&self.sql, start.elapsed(), None);
+        Ok(keys)
+    }
+
+    fn rewritten_sql(&self) -> &str {
+        self.stmt.rewritten_sql()
+    }
+
+    fn returns_rows(&self) -> bool {
+        self.stmt.returns_rows()
+    }
+
+    fn placeholders(&self) -> Vec<Placeholder> {
+        self.stmt.placeholders()
+    }
+
+    fn set_max_rows(&mut self, max_rows: u64) -> Result<()> {
+        self.stmt.set_max_rows(max_rows)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.stmt.reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Column, DataType, MaterializedResultSet};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        calls: Mutex<Vec<(String, Duration, Option<u64>)>>,
+    }
+
+    impl QueryObserver for RecordingObserver {
+        fn on_execute(&self, sql: &str, duration: Duration, rows: Option<u64>) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((sql.to_owned(), duration, rows));
+        }
+    }
+
+    struct MockConnection;
+
+    struct MockStatement;
+
+    impl Connection for MockConnection {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn create(&mut self, _sql: &str) -> Result<Box<dyn Statement + '_>> {
+            Ok(Box::new(MockStatement))
+        }
+
+        fn prepare(&mut self, _sql: &str) -> Result<Box<dyn Statement + '_>> {
+            Ok(Box::new(MockStatement))
+        }
+    }
+
+    impl Statement for MockStatement {
+        fn execute_query(&mut self, _params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+            std::thread::sleep(Duration::from_millis(1));
+            let rows = vec![vec![Value::Int32(1)], vec![Value::Int32(2)]];
+            let meta = vec![Column::new("a", DataType::Integer)];
+            Ok(Box::new(MaterializedResultSet::new(meta, rows)))
+        }
+
+        fn execute_update(&mut self, _params: &[Value]) -> Result<u64> {
+            std::thread::sleep(Duration::from_millis(1));
+            Ok(3)
+        }
+
+        fn rewritten_sql(&self) -> &str {
+            ""
+        }
+    }
+
+    #[test]
+    fn observe_statements() -> Result<()> {
+        let observer = Arc::new(RecordingObserver::default());
+        let mut conn = ObservedConnection::new(Box::new(MockConnection), observer.clone());
+        conn.prepare("SELECT a FROM test")?.execute_query(&[])?;
+        conn.execute("DELETE FROM test", &[])?;
+
+        let calls = observer.calls.lock().unwrap();
+        assert_eq!(2, calls.len());
+        assert_eq!("SELECT a FROM test", calls[0].0);
+        assert_eq!(Some(2), calls[0].2);
+        assert_eq!("DELETE FROM test", calls[1].0);
+        assert_eq!(Some(3), calls[1].2);
+        assert!(calls
+            .iter()
+            .all(|(_, duration, _)| *duration > Duration::from_secs(0)));
+        assert!(conn.as_any().is::<MockConnection>());
+        Ok(())
+    }
+}
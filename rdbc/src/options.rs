@@ -0,0 +1,71 @@
+//! Structured connection options, as an alternative to connection URLs
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::{DEFAULT_CONNECT_TIMEOUT, DEFAULT_KEEPALIVE};
+
+/// Options for `Driver::connect_with`. Unlike a connection URL, the credentials are kept in
+/// separate fields so that they do not need to be embedded in a string that might be logged,
+/// and the `Debug` output omits the password.
+///
+/// Drivers ignore any options that do not apply to them. For example, SQLite only uses
+/// `database` as the path of the database file and `params` as URI parameters, and ignores
+/// the network settings such as `keepalive`.
+///
+/// ```rust
+/// let opts = rdbc::ConnectOptions {
+///     host: Some("localhost".to_owned()),
+///     user: Some("rdbc".to_owned()),
+///     password: Some("secret".to_owned()),
+///     ..Default::default()
+/// };
+/// assert!(!format!("{:?}", opts).contains("secret"));
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct ConnectOptions {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub database: Option<String>,
+    /// Require an encrypted connection
+    pub tls: bool,
+    /// Additional driver-specific parameters, equivalent to the query string of a URL
+    pub params: Vec<(String, String)>,
+    pub connect_timeout: Duration,
+    /// Idle time after which TCP keepalive probes are sent, or `None` to disable keepalives
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            host: None,
+            port: None,
+            user: None,
+            password: None,
+            database: None,
+            tls: false,
+            params: vec![],
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            keepalive: Some(DEFAULT_KEEPALIVE),
+        }
+    }
+}
+
+impl fmt::Debug for ConnectOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectOptions")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("database", &self.database)
+            .field("tls", &self.tls)
+            .field("params", &self.params)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("keepalive", &self.keepalive)
+            .finish()
+    }
+}
@@ -0,0 +1,314 @@
+//! A simple connection pool
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+
+use crate::{Connection, Driver, Error, ReconnectingConnection, Result};
+
+/// A pool of connections to a single database. Connections are opened on demand, up to
+/// `max_size`, and are returned to the pool when the `PooledConnection` is dropped.
+///
+/// Idle connections are validated with `Connection::is_valid` before being handed out and any
+/// connection that is found to be dead is transparently replaced with a fresh one.
+///
+/// Statements registered with `warm` are prepared with `Connection::prepare_cached` on every
+/// connection as it is checked out, so that they are already in the connection's statement
+/// cache when the application prepares them. If a statement fails to prepare on a connection,
+/// the failure is logged and the statement is no longer warmed.
+pub struct Pool {
+    driver: Arc<dyn Driver>,
+    url: String,
+    max_size: usize,
+    retry_statements: bool,
+    state: Mutex<PoolState>,
+}
+
+struct PoolState {
+    /// Connections that are open but not currently in use, along with the number of `warm`
+    /// statements that have been prepared on them
+    idle: Vec<(Box<dyn Connection>, usize)>,
+    /// Total number of open connections, including those currently in use
+    size: usize,
+    /// Statements to prepare on each connection when it is checked out. Statements that failed
+    /// to prepare are replaced with `None`, so that the positions of the others do not change.
+    warm: Vec<Option<String>>,
+}
+
+impl Pool {
+    /// Create a pool that opens connections to `url` using the given driver
+    pub fn new(driver: Arc<dyn Driver>, url: &str, max_size: usize) -> Self {
+        Pool {
+            driver,
+            url: url.to_owned(),
+            max_size,
+            retry_statements: false,
+            state: Mutex::new(PoolState {
+                idle: vec![],
+                size: 0,
+                warm: vec![],
+            }),
+        }
+    }
+
+    /// Set whether statements on the pool's connections reconnect and retry once when the
+    /// connection to the database is lost while they are executing. This is off by default
+    /// because a non-idempotent statement could be applied twice. A query is not retried once
+    /// its rows are being read. See `ReconnectingConnection` for details.
+    pub fn with_statement_retry(mut self, retry: bool) -> Self {
+        self.retry_statements = retry;
+        self
+    }
+
+    /// Get a connection from the pool, opening a new one if there are no idle connections
+    pub fn get(&self) -> Result<PooledConnection<'_>> {
+        let (conn, warmed) = self.checkout()?;
+        Ok(self.prepare_warm(conn, warmed))
+    }
+
+    /// Register statements to be prepared on every connection in the pool. The statements are
+    /// prepared immediately on one connection, so that errors in the SQL are reported here, and
+    /// on each other connection the next time it is checked out.
+    pub fn warm(&self, statements: &[&str]) -> Result<()> {
+        let mut conn = self.get()?;
+        for sql in statements {
+            conn.prepare_cached(sql)?;
+        }
+        let mut state = self.state.lock().unwrap();
+        let warmed = state.warm.len();
+        for sql in statements {
+            if !state.warm.iter().any(|s| s.as_deref() == Some(*sql)) {
+                state.warm.push(Some((*sql).to_owned()));
+            }
+        }
+        // another thread may have added statements that this connection has not prepared
+        if conn.warmed == warmed {
+            conn.warmed = state.warm.len();
+        }
+        Ok(())
+    }
+
+    /// Take an idle connection or open a new one, returning the connection and the number of
+    /// `warm` statements that have been prepared on it
+    fn checkout(&self) -> Result<(Box<dyn Connection>, usize)> {
+        loop {
+            let conn = self.state.lock().unwrap().idle.pop();
+            match conn {
+                Some((mut conn, warmed)) => {
+                    if conn.is_valid() {
+                        return Ok((conn, warmed));
+                    }
+                    // the connection is dead so discard it and try the next one
+                    self.state.lock().unwrap().size -= 1;
+                }
+                None => break,
+            }
+        }
+
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.size >= self.max_size {
+                return Err(Error::General(format!(
+                    "Connection pool exhausted (max_size = {})",
+                    self.max_size
+                )));
+            }
+            state.size += 1;
+        }
+
+        match self.driver.connect(&self.url) {
+            Ok(conn) if self.retry_statements => {
+                let conn = ReconnectingConnection::new(conn, self.driver.clone(), &self.url)
+                    .with_retry(true);
+                Ok((Box::new(conn), 0))
+            }
+            Ok(conn) => Ok((conn, 0)),
+            Err(e) => {
+                self.state.lock().unwrap().size -= 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// Prepare any `warm` statements that have not yet been prepared on the connection
+    fn prepare_warm(
+        &self,
+        mut conn: Box<dyn Connection>,
+        mut warmed: usize,
+    ) -> PooledConnection<'_> {
+        loop {
+            let sql = match self.state.lock().unwrap().warm.get(warmed) {
+                Some(Some(sql)) => sql.clone(),
+                Some(None) => {
+                    warmed += 1;
+                    continue;
+                }
+                None => break,
+            };
+            if let Err(e) = conn.prepare_cached(&sql) {
+                warn!("Failed to prepare warm statement '{}': {:?}", sql, e);
+                self.state.lock().unwrap().warm[warmed] = None;
+            }
+            warmed += 1;
+        }
+        PooledConnection::new(self, conn, warmed)
+    }
+
+    /// The number of idle connections currently held by the pool
+    pub fn idle_count(&self) -> usize {
+        self.state.lock().unwrap().idle.len()
+    }
+
+    fn release(&self, conn: Box<dyn Connection>, warmed: usize) {
+        self.state.lock().unwrap().idle.push((conn, warmed));
+    }
+}
+
+/// A connection borrowed from a `Pool`, which is returned to the pool when dropped
+pub struct PooledConnection<'a> {
+    pool: &'a Pool,
+    conn: Option<Box<dyn Connection>>,
+    warmed: usize,
+}
+
+impl<'a> PooledConnection<'a> {
+    fn new(pool: &'a Pool, conn: Box<dyn Connection>, warmed: usize) -> Self {
+        PooledConnection {
+            pool,
+            conn: Some(conn),
+            warmed,
+        }
+    }
+}
+
+impl<'a> Deref for PooledConnection<'a> {
+    type Target = dyn Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.as_ref().unwrap().as_ref()
+    }
+}
+
+impl<'a> DerefMut for PooledConnection<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn.as_mut().unwrap().as_mut()
+    }
+}
+
+impl<'a> Drop for PooledConnection<'a> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn, self.warmed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDriver;
+
+    #[test]
+    fn reuses_idle_connection() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver.clone(), "mock://", 2);
+        drop(pool.get()?);
+        assert_eq!(1, pool.idle_count());
+        drop(pool.get()?);
+        assert_eq!(1, driver.connections());
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_dead_connection() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver.clone(), "mock://", 1);
+        drop(pool.get()?);
+
+        // forcibly invalidate the idle connection
+        driver.disconnect();
+
+        let mut conn = pool.get()?;
+        assert!(conn.is_valid());
+        assert_eq!(2, driver.connections());
+        Ok(())
+    }
+
+    #[test]
+    fn exhausted() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver, "mock://", 1);
+        let _conn = pool.get()?;
+        assert!(pool.get().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn warm() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver.clone(), "mock://", 2);
+        pool.warm(&["SELECT 1", "SELECT 2"])?;
+        assert_eq!(0, driver.cache_hits());
+
+        // the warmed connection is reused and already has the statement cached
+        let mut conn = pool.get()?;
+        conn.prepare_cached("SELECT 1")?;
+        assert_eq!(1, driver.cache_hits());
+
+        // a new connection is warmed on checkout
+        let mut conn2 = pool.get()?;
+        assert_eq!(2, driver.connections());
+        conn2.prepare_cached("SELECT 2")?;
+        assert_eq!(2, driver.cache_hits());
+        drop(conn);
+        drop(conn2);
+
+        // idle connections are not warmed again
+        let _conn = pool.get()?;
+        assert_eq!(2, driver.cache_hits());
+        Ok(())
+    }
+
+    #[test]
+    fn warm_failure() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver.clone(), "mock://", 2);
+        pool.warm(&["SELECT 1", "SELECT 2"])?;
+        let _conn = pool.get()?;
+
+        // the statement fails to prepare on a new connection, which is still handed out
+        driver.break_statement("SELECT 1");
+        let mut conn2 = pool.get()?;
+        assert_eq!(2, driver.connections());
+        conn2.prepare_cached("SELECT 2")?;
+        assert_eq!(1, driver.cache_hits());
+        assert_eq!(
+            vec![None, Some("SELECT 2".to_owned())],
+            pool.state.lock().unwrap().warm
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn statement_retry() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver.clone(), "mock://", 1).with_statement_retry(true);
+        let mut conn = pool.get()?;
+        let mut stmt = conn.prepare_cached("DELETE FROM test")?;
+        driver.disconnect();
+        assert_eq!(1, stmt.execute_update(&[])?);
+        assert_eq!(2, driver.connections());
+        Ok(())
+    }
+
+    #[test]
+    fn warm_invalid_sql() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let pool = Pool::new(driver, "mock://", 1);
+        assert!(pool.warm(&["INVALID"]).is_err());
+        // the failed statement is not registered, so the pool is still usable
+        drop(pool.get()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+//! A connection that transparently reconnects when the connection to the database is lost
+
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::{
+    CancelToken, Connection, DbError, Dialect, Driver, Error, Notification, Result, ResultSet,
+    ResultSetMetaData, Statement, Value,
+};
+
+/// A connection that opens a new connection to the database when an operation fails and the
+/// underlying connection is no longer valid, as reported by `Connection::is_valid`.
+///
+/// Statements created by a `ReconnectingConnection` keep the statement prepared on the
+/// underlying connection and only prepare it again after an execution fails, on the new
+/// connection if the connection was lost. Query results are streamed from the underlying
+/// statement, so only a query that fails before any rows are read can be retried; a failure
+/// while reading the rows is returned to the caller.
+///
+/// By default the operation that detected the lost connection still fails, since retrying it
+/// could execute a non-idempotent statement twice if the connection dropped after the database
+/// had applied it. Use `with_retry` to retry the operation once on the new connection instead.
+/// Session state such as variables set with `set_var` is not restored after a reconnect.
+pub struct ReconnectingConnection {
+    conn: Box<dyn Connection>,
+    driver: Arc<dyn Driver>,
+    url: String,
+    retry: bool,
+}
+
+impl ReconnectingConnection {
+    /// Wrap a connection that was opened to `url` using the given driver
+    pub fn new(conn: Box<dyn Connection>, driver: Arc<dyn Driver>, url: &str) -> Self {
+        ReconnectingConnection {
+            conn,
+            driver,
+            url: url.to_owned(),
+            retry: false,
+        }
+    }
+
+    /// Open a connection to `url` using the given driver
+    pub fn connect(driver: Arc<dyn Driver>, url: &str) -> Result<Self> {
+        let conn = driver.connect(url)?;
+        Ok(Self::new(conn, driver, url))
+    }
+
+    /// Set whether an operation that fails because the connection was lost is retried once
+    /// after reconnecting
+    pub fn with_retry(mut self, retry: bool) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run an operation against the underlying connection, reconnecting if it fails because
+    /// the connection was lost
+    fn run<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut dyn Connection) -> Result<T>,
+    {
+        match f(self.conn.as_mut()) {
+            Err(e) => {
+                if self.reconnect() && self.retry {
+                    f(self.conn.as_mut())
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Replace the underlying connection with a new one if it is no longer valid, returning
+    /// whether it was replaced
+    fn reconnect(&mut self) -> bool {
+        if self.conn.is_valid() {
+            return false;
+        }
+        match self.driver.connect(&self.url) {
+            Ok(conn) => {
+                self.conn = conn;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn statement(&mut self, sql: &str, prepared: bool) -> Result<Box<dyn Statement + '_>> {
+        let mut stmt = ReconnectingStatement {
+            stmt: None,
+            conn: self,
+            sql: sql.to_owned(),
+            rewritten: String::new(),
+            prepared,
+            max_rows: None,
+        };
+        // prepare the statement up front so that errors in the SQL are reported here and the
+        // rewritten SQL of the underlying statement is known
+        stmt.rewritten = stmt.run(|stmt| Ok(stmt.rewritten_sql().to_owned()))?;
+        Ok(Box::new(stmt))
+    }
+}
+
+impl Connection for ReconnectingConnection {
+    fn create(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.statement(sql, false)
+    }
+
+    fn prepare(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.statement(sql, true)
+    }
+
+    fn prepare_cached(&mut self, sql: &str) -> Result<Box<dyn Statement + '_>> {
+        self.statement(sql, true)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.conn.as_any()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self.conn.as_any_mut()
+    }
+
+    fn dialect(&self) -> &dyn Dialect {
+        self.conn.dialect()
+    }
+
+    fn is_valid(&mut self) -> bool {
+        self.conn.is_valid()
+    }
+
+    fn set_placeholder(&mut self, ch: char) -> Result<()> {
+        self.conn.set_placeholder(ch)
+    }
+
+    fn set_read_only(&mut self, on: bool) -> Result<()> {
+        self.conn.set_read_only(on)
+    }
+
+    fn set_var(&mut self, name: &str, value: Value) -> Result<()> {
+        self.conn.set_var(name, value)
+    }
+
+    fn tables(&mut self) -> Result<Vec<String>> {
+        self.run(|conn| conn.tables())
+    }
+
+    fn columns(&mut self, table: &str) -> Result<Box<dyn ResultSetMetaData>> {
+        self.run(|conn| conn.columns(table))
+    }
+
+    fn listen(&mut self, channel: &str) -> Result<()> {
+        self.conn.listen(channel)
+    }
+
+    fn notifications(&mut self) -> Result<Box<dyn Iterator<Item = Notification>>> {
+        self.conn.notifications()
+    }
+
+    fn cancel_token(&self) -> Result<CancelToken> {
+        self.conn.cancel_token()
+    }
+
+    fn last_error(&self) -> Option<DbError> {
+        self.conn.last_error()
+    }
+}
+
+struct ReconnectingStatement<'a> {
+    /// The statement prepared on the underlying connection, or `None` if it has to be prepared
+    /// again because the last execution failed. This borrows the connection owned by `conn`,
+    /// so it must be dropped before the connection is used or replaced.
+    stmt: Option<Box<dyn Statement + 'a>>,
+    conn: &'a mut ReconnectingConnection,
+    sql: String,
+    /// The SQL as rewritten by the underlying connection's statement
+    rewritten: String,
+    /// Whether the statement was created with `prepare` rather than `create`
+    prepared: bool,
+    max_rows: Option<u64>,
+}
+
+impl<'a> ReconnectingStatement<'a> {
+    /// Prepare the statement on the underlying connection unless it is already prepared
+    fn prepare(&mut self) -> Result<&mut dyn Statement> {
+        if self.stmt.is_none() {
+            // SAFETY: the connection is boxed and owned by `self.conn`, which is borrowed for
+            // `'a`, so it does not move and nothing else can use it while this statement
+            // exists. The prepared statement is the only way the connection is reached until
+            // `recover` drops the statement, before it checks or replaces the connection.
+            let conn = unsafe { &mut *(self.conn.conn.as_mut() as *mut dyn Connection) };
+            let mut stmt = if self.prepared {
+                conn.prepare_cached(&self.sql)?
+            } else {
+                conn.create(&self.sql)?
+            };
+            if let Some(max_rows) = self.max_rows {
+                stmt.set_max_rows(max_rows)?;
+            }
+            self.stmt = Some(stmt);
+        }
+        Ok(self.stmt.as_mut().unwrap().as_mut())
+    }
+
+    /// Drop the statement after an execution failed, reconnecting if the connection was lost.
+    /// Returns the error unless the execution should be retried on the new connection.
+    fn recover(&mut self, e: Error) -> Result<()> {
+        self.stmt = None;
+        if self.conn.reconnect() && self.conn.retry {
+            Ok(())
+        } else {
+            Err(e)
+        }
+    }
+
+    fn run<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut dyn Statement) -> Result<T>,
+    {
+        match self.prepare().and_then(|stmt| f(stmt)) {
+            Err(e) => {
+                self.recover(e)?;
+                self.prepare().and_then(f)
+            }
+            result => result,
+        }
+    }
+}
+
+impl<'a> Statement for ReconnectingStatement<'a> {
+    fn execute_query(&mut self, params: &[Value]) -> Result<Box<dyn ResultSet + '_>> {
+        let this: *mut Self = self;
+        // SAFETY: the result set is only borrowed from `self` when it is returned, and `self`
+        // is only used again when there is no result set. This works around the borrow checker
+        // extending a borrow that is returned from one branch to the other.
+        match unsafe { &mut *this }
+            .prepare()
+            .and_then(|stmt| stmt.execute_query(params))
+        {
+            Ok(rs) => return Ok(rs),
+            Err(e) => self.recover(e)?,
+        }
+        self.prepare()?.execute_query(params)
+    }
+
+    fn execute_update(&mut self, params: &[Value]) -> Result<u64> {
+        self.run(|stmt| stmt.execute_update(params))
+    }
+
+    fn execute_many(&mut self, param_sets: &[Vec<Value>]) -> Result<u64> {
+        self.run(|stmt| stmt.execute_many(param_sets))
+    }
+
+    fn execute_returning_keys(&mut self, params: &[Value]) -> Result<Vec<Value>> {
+        self.run(|stmt| stmt.execute_returning_keys(params))
+    }
+
+    fn rewritten_sql(&self) -> &str {
+        &self.rewritten
+    }
+
+    fn set_max_rows(&mut self, max_rows: u64) -> Result<()> {
+        if let Some(stmt) = self.stmt.as_mut() {
+            stmt.set_max_rows(max_rows)?;
+        }
+        self.max_rows = Some(max_rows);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        match self.stmt.as_mut() {
+            Some(stmt) => stmt.reset(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDriver;
+
+    #[test]
+    fn reprepare_after_disconnect() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let mut conn = ReconnectingConnection::connect(driver.clone(), "mock://")?.with_retry(true);
+        let mut stmt = conn.prepare("SELECT a FROM test")?;
+
+        // the connection drops between prepare and execute
+        driver.disconnect();
+
+        let mut rs = stmt.execute_query(&[])?;
+        assert!(rs.next()?);
+        assert_eq!(Some(1), rs.get_i32(0)?);
+        assert_eq!(2, driver.connections());
+        assert_eq!(1, driver.queries());
+        Ok(())
+    }
+
+    #[test]
+    fn prepare_once() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let mut conn = ReconnectingConnection::connect(driver.clone(), "mock://")?.with_retry(true);
+        let mut stmt = conn.prepare("DELETE FROM test")?;
+        stmt.execute_update(&[])?;
+        stmt.execute_update(&[])?;
+        assert_eq!(1, driver.prepares());
+
+        // the statement is prepared again on the new connection
+        driver.disconnect();
+        stmt.execute_update(&[])?;
+        stmt.execute_update(&[])?;
+        assert_eq!(2, driver.prepares());
+        assert_eq!(4, driver.updates());
+        Ok(())
+    }
+
+    #[test]
+    fn no_retry_by_default() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let mut conn = ReconnectingConnection::connect(driver.clone(), "mock://")?;
+        let mut stmt = conn.prepare("DELETE FROM test")?;
+        driver.disconnect();
+
+        // the failed execution is not retried but the statement is usable on the new connection
+        assert!(stmt.execute_update(&[]).is_err());
+        assert_eq!(2, driver.connections());
+        assert_eq!(0, driver.updates());
+        assert_eq!(1, stmt.execute_update(&[])?);
+        Ok(())
+    }
+
+    #[test]
+    fn rewritten_sql_of_underlying_statement() -> Result<()> {
+        let driver = Arc::new(MockDriver::default());
+        let mut conn = ReconnectingConnection::connect(driver, "mock://")?;
+        assert_eq!("SELECT $1", conn.prepare("SELECT ?")?.rewritten_sql());
+        assert_eq!("SELECT $1", conn.create("SELECT ?")?.rewritten_sql());
+        Ok(())
+    }
+}
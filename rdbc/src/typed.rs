@@ -0,0 +1,362 @@
+//! Mapping rows to Rust types
+
+use std::marker::PhantomData;
+
+use crate::{Connection, Error, Interval, Result, Row, Statement, Value};
+
+/// A type that can be read from a single column of a row
+pub trait FromColumn: Sized {
+    /// Read the value of a column, returning `None` if it is NULL
+    fn from_nullable(row: &Row, i: u64) -> Result<Option<Self>>;
+
+    /// Read the value of a column, failing if it is NULL. Use `Option<T>` to read columns that
+    /// may be NULL.
+    fn from_column(row: &Row, i: u64) -> Result<Self> {
+        Self::from_nullable(row, i)?
+            .ok_or_else(|| Error::Conversion(format!("Unexpected NULL in column {}", i)))
+    }
+}
+
+impl<T: FromColumn> FromColumn for Option<T> {
+    fn from_nullable(row: &Row, i: u64) -> Result<Option<Self>> {
+        T::from_nullable(row, i).map(Some)
+    }
+}
+
+macro_rules! impl_from_column {
+    ($($ty: ty => $fn: ident),*) => {
+        $(
+            impl FromColumn for $ty {
+                fn from_nullable(row: &Row, i: u64) -> Result<Option<Self>> {
+                    row.$fn(i)
+                }
+            }
+        )*
+    }
+}
+
+impl_from_column! {
+    i8 => get_i8,
+    i16 => get_i16,
+    i32 => get_i32,
+    i64 => get_i64,
+    u8 => get_byte,
+    u32 => get_u32,
+    f32 => get_f32,
+    f64 => get_f64,
+    String => get_string,
+    char => get_char,
+    Vec<u8> => get_bytes,
+    Interval => get_interval,
+    Vec<Value> => get_record
+}
+
+#[cfg(feature = "bigdecimal")]
+impl_from_column! {
+    bigdecimal::BigDecimal => get_big_decimal
+}
+
+/// A user-defined type that is bound as one of the existing `Value` variants, for example a
+/// newtype around `f64` that is bound as `Value::Float64`
+///
+/// ```rust,ignore
+/// conn.execute("INSERT INTO readings (temp) VALUES (?)", &[temp.to_rdbc_value()])?;
+/// ```
+///
+/// Enums are usually stored by variant name in a text column, or by discriminant in an integer
+/// column. The `rdbc_enum!` macro implements `ToRdbcValue` and `FromRdbcColumn` for an enum
+/// without fields that is stored by variant name; other mappings implement both traits by
+/// hand:
+///
+/// ```rust
+/// use rdbc::{Error, FromRdbcColumn, Result, ToRdbcValue, Value};
+///
+/// enum Priority {
+///     Low = 1,
+///     High = 2,
+/// }
+///
+/// impl ToRdbcValue for Priority {
+///     fn to_rdbc_value(&self) -> Value {
+///         match self {
+///             Priority::Low => Value::Int32(1),
+///             Priority::High => Value::Int32(2),
+///         }
+///     }
+/// }
+///
+/// impl FromRdbcColumn for Priority {
+///     fn from_rdbc_value(value: Value) -> Result<Self> {
+///         match value {
+///             Value::Int32(1) => Ok(Priority::Low),
+///             Value::Int32(2) => Ok(Priority::High),
+///             other => Err(Error::Conversion(format!("Invalid priority: {:?}", other))),
+///         }
+///     }
+/// }
+/// ```
+pub trait ToRdbcValue {
+    fn to_rdbc_value(&self) -> Value;
+}
+
+/// A user-defined type that is read from one of the existing `Value` variants. Implementing
+/// this trait also implements `FromColumn`, so the type can be used with `FromRow` and
+/// `Connection::prepare_typed`. NULL columns are handled by `FromColumn` and are never passed
+/// to `from_rdbc_value`.
+pub trait FromRdbcColumn: Sized {
+    fn from_rdbc_value(value: Value) -> Result<Self>;
+}
+
+impl<T: FromRdbcColumn> FromColumn for T {
+    fn from_nullable(row: &Row, i: u64) -> Result<Option<Self>> {
+        match row.get_value(i)? {
+            Value::Null => Ok(None),
+            value => T::from_rdbc_value(value).map(Some),
+        }
+    }
+}
+
+/// Implement `ToRdbcValue` and `FromRdbcColumn` for an enum without fields, binding each
+/// variant as a `Value::String` containing the variant name. Reading a string that is not
+/// one of the listed variant names fails with a conversion error.
+///
+/// ```rust
+/// #[derive(Debug, PartialEq)]
+/// enum Color {
+///     Red,
+///     Green,
+/// }
+///
+/// rdbc::rdbc_enum!(Color { Red, Green });
+///
+/// use rdbc::{FromRdbcColumn, ToRdbcValue, Value};
+/// assert_eq!(Value::String("Red".to_owned()), Color::Red.to_rdbc_value());
+/// assert_eq!(Color::Green, Color::from_rdbc_value("Green".into()).unwrap());
+/// ```
+#[macro_export]
+macro_rules! rdbc_enum {
+    ($ty: ident { $($variant: ident),* $(,)? }) => {
+        impl $crate::ToRdbcValue for $ty {
+            fn to_rdbc_value(&self) -> $crate::Value {
+                let name = match self {
+                    $($ty::$variant => stringify!($variant),)*
+                };
+                $crate::Value::String(name.to_owned())
+            }
+        }
+
+        impl $crate::FromRdbcColumn for $ty {
+            fn from_rdbc_value(value: $crate::Value) -> $crate::Result<Self> {
+                match value {
+                    $($crate::Value::String(ref s) if s == stringify!($variant) => {
+                        Ok($ty::$variant)
+                    })*
+                    other => Err($crate::Error::Conversion(format!(
+                        "Cannot convert {:?} to {}",
+                        other,
+                        stringify!($ty)
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+/// A type that can be created from a row, such as a tuple with one element per column
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self>;
+}
+
+macro_rules! impl_from_row {
+    ($($name: ident: $i: expr),*) => {
+        impl<$($name: FromColumn),*> FromRow for ($($name,)*) {
+            fn from_row(row: &Row) -> Result<Self> {
+                Ok(($($name::from_column(row, $i)?,)*))
+            }
+        }
+    }
+}
+
+impl_from_row!(A: 0);
+impl_from_row!(A: 0, B: 1);
+impl_from_row!(A: 0, B: 1, C: 2);
+impl_from_row!(A: 0, B: 1, C: 2, D: 3);
+impl_from_row!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_from_row!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_from_row!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_from_row!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// A statement whose rows are mapped to `T`, created with `Connection::prepare_typed`
+pub struct TypedStatement<'a, T> {
+    stmt: Box<dyn Statement + 'a>,
+    row_type: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: FromRow> TypedStatement<'a, T> {
+    pub fn new(stmt: Box<dyn Statement + 'a>) -> Self {
+        TypedStatement {
+            stmt,
+            row_type: PhantomData,
+        }
+    }
+
+    /// Execute the query and map every row of the result set to `T`
+    pub fn query(&mut self, params: &[Value]) -> Result<Vec<T>> {
+        self.stmt.query_map(params, T::from_row)
+    }
+}
+
+impl<'a> dyn Connection + 'a {
+    /// Prepare a statement whose rows are mapped to `T`
+    ///
+    /// ```rust,ignore
+    /// let mut stmt = conn.prepare_typed::<(i32, Option<String>)>("SELECT a, b FROM test")?;
+    /// let rows = stmt.query(&[])?;
+    /// ```
+    pub fn prepare_typed<T: FromRow>(&mut self, sql: &str) -> Result<TypedStatement<'_, T>> {
+        Ok(TypedStatement::new(self.prepare(sql)?))
+    }
+}
+
+/// A statement and its parameters, as created by the `query!` macro in the `rdbc-macros` crate
+/// after the SQL has been checked against a database at build time
+#[derive(Debug, Clone)]
+pub struct Query {
+    sql: String,
+    params: Vec<Value>,
+}
+
+impl Query {
+    pub fn new(sql: &str, params: Vec<Value>) -> Self {
+        Query {
+            sql: sql.to_owned(),
+            params,
+        }
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    /// Execute the statement, returning the number of rows affected
+    pub fn execute(&self, conn: &mut dyn Connection) -> Result<u64> {
+        conn.execute(&self.sql, &self.params)
+    }
+
+    /// Execute the query and map every row of the result set to `T`
+    pub fn fetch_all<T: FromRow>(&self, conn: &mut dyn Connection) -> Result<Vec<T>> {
+        conn.prepare_typed::<T>(&self.sql)?.query(&self.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Column, DataType, MaterializedResultSet, ResultSet};
+
+    #[test]
+    fn map_rows() -> Result<()> {
+        let mut rs: Box<dyn ResultSet> = Box::new(MaterializedResultSet::new(
+            vec![
+                Column::new("a", DataType::Integer),
+                Column::new("b", DataType::Utf8),
+            ],
+            vec![
+                vec![Value::Int32(1), Value::String("one".to_owned())],
+                vec![Value::Int32(2), Value::Null],
+            ],
+        ));
+
+        let row = rs.next_row()?.unwrap();
+        assert_eq!((1, "one".to_owned()), <(i32, String)>::from_row(&row)?);
+
+        let row = rs.next_row()?.unwrap();
+        assert_eq!((2, None), <(i64, Option<String>)>::from_row(&row)?);
+        assert!(<(i32, String)>::from_row(&row).is_err());
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Celsius(f64);
+
+    impl ToRdbcValue for Celsius {
+        fn to_rdbc_value(&self) -> Value {
+            Value::Float64(self.0)
+        }
+    }
+
+    impl FromRdbcColumn for Celsius {
+        fn from_rdbc_value(value: Value) -> Result<Self> {
+            match value {
+                Value::Float64(n) => Ok(Celsius(n)),
+                other => Err(Error::Conversion(format!(
+                    "Cannot convert {:?} to Celsius",
+                    other
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn custom_types() -> Result<()> {
+        let value = Celsius(21.5).to_rdbc_value();
+        assert_eq!(Value::Float64(21.5), value);
+
+        let mut rs: Box<dyn ResultSet> = Box::new(MaterializedResultSet::new(
+            vec![
+                Column::new("temp", DataType::Double),
+                Column::new("name", DataType::Utf8),
+            ],
+            vec![
+                vec![value, Value::String("kitchen".to_owned())],
+                vec![Value::Null, Value::Null],
+            ],
+        ));
+
+        let row = rs.next_row()?.unwrap();
+        assert_eq!(
+            (Celsius(21.5), "kitchen".to_owned()),
+            <(Celsius, String)>::from_row(&row)?
+        );
+        assert!(Celsius::from_column(&row, 1).is_err());
+
+        let row = rs.next_row()?.unwrap();
+        assert_eq!(None, <Option<Celsius>>::from_column(&row, 0)?);
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Status {
+        Active,
+        Suspended,
+    }
+
+    rdbc_enum!(Status { Active, Suspended });
+
+    #[test]
+    fn enum_values() -> Result<()> {
+        assert_eq!(
+            Value::String("Suspended".to_owned()),
+            Status::Suspended.to_rdbc_value()
+        );
+
+        let mut rs: Box<dyn ResultSet> = Box::new(MaterializedResultSet::new(
+            vec![Column::new("status", DataType::Utf8)],
+            vec![
+                vec![Status::Active.to_rdbc_value()],
+                vec![Value::String("Deleted".to_owned())],
+            ],
+        ));
+
+        let row = rs.next_row()?.unwrap();
+        assert_eq!(Status::Active, Status::from_column(&row, 0)?);
+
+        let row = rs.next_row()?.unwrap();
+        assert!(Status::from_column(&row, 0).is_err());
+        Ok(())
+    }
+}